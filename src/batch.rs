@@ -0,0 +1,128 @@
+use std::marker::PhantomData;
+
+use codec::Decode;
+
+use crate::{
+    model::chain::{BlockRef, QueryKey, StorageQuery},
+    Client,
+};
+
+/// 批量存储查询
+/// 先登记所有存储项，再通过一次 RPC 请求获取
+#[derive(Debug, Default, Clone)]
+pub struct StorageBatch {
+    queries: Vec<StorageQuery>,
+}
+
+/// 批量查询中某一项的句柄，用于解码对应的结果
+#[derive(Debug, Clone, Copy)]
+pub struct BatchItem<V> {
+    index: usize,
+    _value: PhantomData<fn() -> V>,
+}
+
+/// 批量查询的结果
+#[derive(Debug, Clone)]
+pub struct BatchResult {
+    values: Vec<Option<Vec<u8>>>,
+}
+
+impl StorageBatch {
+    pub fn new() -> Self {
+        Self { queries: vec![] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+
+    /// 查询 StorageValue
+    pub fn value<V: Decode>(
+        &mut self,
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+    ) -> BatchItem<V> {
+        self.push(StorageQuery::Value {
+            storage_prefix,
+            storage_key_name,
+        })
+    }
+
+    /// 查询 StorageMap
+    pub fn map<V: Decode>(
+        &mut self,
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+        key: QueryKey,
+    ) -> BatchItem<V> {
+        self.push(StorageQuery::Map {
+            storage_prefix,
+            storage_key_name,
+            key,
+        })
+    }
+
+    /// 查询 StorageDoubleMap
+    pub fn double_map<V: Decode>(
+        &mut self,
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+        first: QueryKey,
+        second: QueryKey,
+    ) -> BatchItem<V> {
+        self.push(StorageQuery::DoubleMap {
+            storage_prefix,
+            storage_key_name,
+            first,
+            second,
+        })
+    }
+
+    /// 查询 StorageNMap
+    pub fn n_map<V: Decode>(
+        &mut self,
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+        keys: Vec<QueryKey>,
+    ) -> BatchItem<V> {
+        self.push(StorageQuery::NMap {
+            storage_prefix,
+            storage_key_name,
+            keys,
+        })
+    }
+
+    /// 发送请求
+    pub async fn fetch(self, client: &Client, at: Option<BlockRef>) -> anyhow::Result<BatchResult> {
+        let values = client.get_storage_many(self.queries, at).await?;
+        Ok(BatchResult { values })
+    }
+
+    fn push<V: Decode>(&mut self, query: StorageQuery) -> BatchItem<V> {
+        self.queries.push(query);
+        BatchItem {
+            index: self.queries.len() - 1,
+            _value: PhantomData,
+        }
+    }
+}
+
+impl BatchResult {
+    /// 解码某一项的结果，存储不存在时返回 None
+    pub fn get<V: Decode>(&self, item: &BatchItem<V>) -> anyhow::Result<Option<V>> {
+        match self.values.get(item.index) {
+            Some(Some(storage)) => Ok(Some(Decode::decode(&mut storage.as_slice())?)),
+            Some(None) => Ok(None),
+            None => Err(anyhow::anyhow!("batch item {} out of range", item.index)),
+        }
+    }
+
+    /// 解码某一项的结果，存储不存在时返回默认值
+    pub fn get_or_default<V: Decode + Default>(&self, item: &BatchItem<V>) -> anyhow::Result<V> {
+        Ok(self.get(item)?.unwrap_or_default())
+    }
+}
@@ -11,3 +11,6 @@ pub static KERINGS: Lazy<Mutex<HashMap<String, Pair>>> = Lazy::new(|| {
 
 /// 金额单位
 pub const UNIT: u64 = 1_000_000_000_000;
+
+/// 原生代币精度，UNIT = 10 ^ DECIMALS
+pub const DECIMALS: u8 = 12;
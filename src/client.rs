@@ -1,19 +1,33 @@
 use crate::{
     account,
-    model::chain::{Command, QueryKey, ChainApi, WeteeConfig},
+    model::chain::{
+        BlockRef, ChainBlock, Command, QueryKey, ChainApi, StorageChange, StorageQuery, StorageReadProof,
+        StorageStream, WeteeConfig,
+    },
 };
 
-use codec::Decode;
+use codec::{Decode, Encode};
+use frame_metadata::{
+    v14::{RuntimeMetadataV14, StorageEntryType, StorageHasher},
+    RuntimeMetadata, RuntimeMetadataPrefixed,
+};
+use futures::channel::mpsc;
 use once_cell::sync::Lazy;
-use std::sync::Mutex;
+use serde::Deserialize;
+use sp_core::{
+    hashing::{blake2_128, blake2_256, twox_128, twox_256, twox_64},
+    storage::{StorageChangeSet, StorageKey},
+    Bytes,
+};
+use std::{collections::HashMap, sync::Mutex};
 use substrate_api_client::{
-    rpc::JsonrpseeClient, Api, ac_primitives::ExtrinsicSigner, GetStorage,SubmitAndWatchUntilSuccess, GetChainInfo,
+    ac_node_api::Metadata, rpc::{HandleSubscription, JsonrpseeClient, Request, Subscribe}, Api, ac_primitives::{rpc_params, ExtrinsicSigner}, GetStorage,SubmitAndWatchUntilSuccess, GetChainInfo,
 };
 use tokio::sync::{
     mpsc::{channel, Sender},
     oneshot,
 };
-use wetee_runtime::RuntimeCall;
+use wetee_runtime::{BlockNumber, Hash, RuntimeCall};
 
 /// 区块链连接
 #[derive(Debug)]
@@ -71,6 +85,75 @@ impl Client {
         Ok(s)
     }
 
+    /// 获取指定高度的区块、事件和时间戳，区块不存在时返回 None
+    pub async fn get_block(&self, number: BlockNumber) -> anyhow::Result<Option<ChainBlock>> {
+        let sender = self.get_sender()?;
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = Command::QueryBlock {
+            number,
+            resp: resp_tx,
+        };
+        sender.send(cmd).await.unwrap();
+
+        resp_rx.await.unwrap()
+    }
+
+    /// 获取指定区块所用 runtime 的元数据，用于解码 runtime 升级前的区块
+    pub async fn get_metadata_at(&self, hash: Hash) -> anyhow::Result<Metadata> {
+        let sender = self.get_sender()?;
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = Command::QueryMetadataAt {
+            hash,
+            resp: resp_tx,
+        };
+        sender.send(cmd).await.unwrap();
+
+        resp_rx.await.unwrap()
+    }
+
+    /// 获取包含类型信息的原始元数据，hash 为 None 时为最新区块
+    pub async fn get_runtime_metadata(&self, hash: Option<Hash>) -> anyhow::Result<RuntimeMetadataV14> {
+        let sender = self.get_sender()?;
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = Command::QueryRuntimeMetadata {
+            hash,
+            resp: resp_tx,
+        };
+        sender.send(cmd).await.unwrap();
+
+        resp_rx.await.unwrap()
+    }
+
+    /// 读取运行时常量，hash 为 None 时为最新区块
+    pub async fn get_constant<C: Decode>(
+        &self,
+        pallet: &str,
+        name: &str,
+        hash: Option<Hash>,
+    ) -> anyhow::Result<C> {
+        let metadata = self.get_runtime_metadata(hash).await?;
+        let constant = metadata
+            .pallets
+            .iter()
+            .find(|p| p.name == pallet)
+            .and_then(|p| p.constants.iter().find(|c| c.name == name))
+            .ok_or_else(|| anyhow::anyhow!("constant {}::{} not found", pallet, name))?;
+        Ok(C::decode(&mut constant.value.as_slice())?)
+    }
+
+    /// 预计出块时间 (毫秒)，为 Timestamp::MinimumPeriod 的两倍
+    /// 常量只在 runtime 升级时变化，每个客户端只读取一次
+    pub async fn block_time(&self) -> anyhow::Result<u64> {
+        let cached = BLOCK_TIMES.lock().unwrap().get(&self.index).copied();
+        if let Some(time) = cached {
+            return Ok(time);
+        }
+        let period: u64 = self.get_constant("Timestamp", "MinimumPeriod", None).await?;
+        let time = period.saturating_mul(2);
+        BLOCK_TIMES.lock().unwrap().insert(self.index, time);
+        Ok(time)
+    }
+
     pub async fn start(&mut self) -> anyhow::Result<bool, anyhow::Error> {
         let url = self.get_url()?;
         let client = JsonrpseeClient::new(url.as_str()).unwrap();
@@ -86,70 +169,73 @@ impl Client {
 
                     let _ = resp.send(Ok(h.number.try_into().unwrap()));
                 }
+                Command::QueryBlock { number, resp } => {
+                    let _ = resp.send(query_block(&api, number));
+                }
+                Command::QueryMetadataAt { hash, resp } => {
+                    let _ = resp.send(query_metadata_at(&api, hash));
+                }
+                Command::QueryRuntimeMetadata { hash, resp } => {
+                    let _ = resp.send(query_runtime_metadata(&api, hash));
+                }
                 Command::QueryValue {
                     storage_prefix,
                     storage_key_name,
+                    at,
                     resp,
                 } => {
+                    let at = match resolve_block(&api, at) {
+                        Ok(h) => h,
+                        Err(e) => {
+                            let _ = resp.send(Err(e));
+                            continue;
+                        }
+                    };
                     let storagekey = api
                         .metadata()
                         .storage_value_key(storage_prefix, storage_key_name)
                         .unwrap();
                     let s = api
-                        .get_opaque_storage_by_key(storagekey, None)
+                        .get_opaque_storage_by_key(storagekey, at)
                         .unwrap();
                     let _ = resp.send(Ok(s));
                 }
                 // 查询map所有值
-                Command::QueryMapAll { storage_prefix, storage_key_name, resp } => {
+                Command::QueryMapAll { storage_prefix, storage_key_name, at, resp } => {
+                    let at = match resolve_block(&api, at) {
+                        Ok(h) => h,
+                        Err(e) => {
+                            let _ = resp.send(Err(e));
+                            continue;
+                        }
+                    };
                     let storagekey = api
                         .metadata()
                         .storage_map_key_prefix(storage_prefix, storage_key_name)
                         .unwrap();
-                    let storage_keys = api
-                        .get_storage_keys_paged(Some(storagekey), 1000, None, None)
-                        .unwrap();
-
-                    let mut results = vec![];
-                    for storage_key in storage_keys.iter() {
-                        let storage_data: Option<Vec<u8>> = api
-                            .get_opaque_storage_by_key(storage_key.clone(), None)
-                            .unwrap();
-                        let hash = "0x".to_owned() + &hex::encode(storage_key.clone().0);
-                        match storage_data {
-                            Some(storage) => results.push((hash, storage)),
-                            None => {}
-                        }
-                    }
 
-                    let _ = resp.send(Ok(results));
+                    let _ = resp.send(query_prefix(&api, storagekey, at));
                 }
                 Command::QueryMap {
                     storage_prefix,
                     storage_key_name,
                     resp,
                     key,
+                    at,
                 } => {
-                    let storagekey = match key {
-                        QueryKey::U64Key(v) => api
-                            .metadata()
-                            .storage_map_key(storage_prefix, storage_key_name, v)
-                            .unwrap(),
-                        QueryKey::U32Key(v) => api
-                            .metadata()
-                            .storage_map_key(storage_prefix, storage_key_name, v)
-                            .unwrap(),
-                        QueryKey::StrKey(v) => api
-                            .metadata()
-                            .storage_map_key(storage_prefix, storage_key_name, v)
-                            .unwrap(),
-                        QueryKey::AccountId(v) => api
-                            .metadata()
-                            .storage_map_key(storage_prefix, storage_key_name, v)
-                            .unwrap(),
+                    let at = match resolve_block(&api, at) {
+                        Ok(h) => h,
+                        Err(e) => {
+                            let _ = resp.send(Err(e));
+                            continue;
+                        }
                     };
+                    let storagekey = api
+                        .metadata()
+                        .storage_map_key(storage_prefix, storage_key_name, key)
+                        .unwrap();
                     let s = api
-                        .get_opaque_storage_by_key(storagekey, None)
+                        .get_opaque_storage_by_key(storagekey, at)
                         .unwrap();
                     let _ = resp.send(Ok(s));
                 }
@@ -158,84 +244,22 @@ impl Client {
                     storage_key_name,
                     first,
                     second,
+                    at,
                     resp,
                 } => {
-                    let storagekey = match first {
-                        QueryKey::U64Key(v) => match second {
-                            QueryKey::U64Key(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                            QueryKey::U32Key(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                            QueryKey::StrKey(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                            QueryKey::AccountId(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                        },
-                        QueryKey::U32Key(v) => match second {
-                            QueryKey::U64Key(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                            QueryKey::U32Key(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                            QueryKey::StrKey(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                            QueryKey::AccountId(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                        },
-                        QueryKey::StrKey(v) => match second {
-                            QueryKey::U64Key(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                            QueryKey::U32Key(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                            QueryKey::StrKey(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                            QueryKey::AccountId(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                        },
-                        QueryKey::AccountId(v) => match second {
-                            QueryKey::U64Key(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                            QueryKey::U32Key(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                            QueryKey::StrKey(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                            QueryKey::AccountId(v2) => api
-                                .metadata()
-                                .storage_double_map_key(storage_prefix, storage_key_name, v, v2)
-                                .unwrap(),
-                        },
+                    let at = match resolve_block(&api, at) {
+                        Ok(h) => h,
+                        Err(e) => {
+                            let _ = resp.send(Err(e));
+                            continue;
+                        }
                     };
+                    let storagekey = api
+                        .metadata()
+                        .storage_double_map_key(storage_prefix, storage_key_name, first, second)
+                        .unwrap();
                     let s = api
-                        .get_opaque_storage_by_key(storagekey, None)
+                        .get_opaque_storage_by_key(storagekey, at)
                         .unwrap();
                     let _ = resp.send(Ok(s));
                 }
@@ -243,39 +267,71 @@ impl Client {
                     storage_prefix,
                     storage_key_name,
                     first,
+                    at,
                     resp,
                 } => {
-                    let storagekey = match first {
-                        QueryKey::U64Key(v) => api
-                            .get_storage_double_map_key_prefix(storage_prefix, storage_key_name, v)
-                            .unwrap(),
-                        QueryKey::U32Key(v) => api
-                            .get_storage_double_map_key_prefix(storage_prefix, storage_key_name, v)
-                            .unwrap(),
-                        QueryKey::StrKey(v) => api
-                            .get_storage_double_map_key_prefix(storage_prefix, storage_key_name, v)
-                            .unwrap(),
-                        QueryKey::AccountId(v) => api
-                            .get_storage_double_map_key_prefix(storage_prefix, storage_key_name, v)
-                            .unwrap(),
+                    let at = match resolve_block(&api, at) {
+                        Ok(h) => h,
+                        Err(e) => {
+                            let _ = resp.send(Err(e));
+                            continue;
+                        }
                     };
-                    let storage_keys = api
-                        .get_storage_keys_paged(Some(storagekey), 1000, None, None)
+                    let storagekey = api
+                        .get_storage_double_map_key_prefix(storage_prefix, storage_key_name, first)
                         .unwrap();
 
-                    let mut results = vec![];
-                    for storage_key in storage_keys.iter() {
-                        let storage_data: Option<Vec<u8>> = api
-                            .get_opaque_storage_by_key(storage_key.clone(), None)
-                            .unwrap();
-                        let hash = "0x".to_owned() + &hex::encode(storage_key.clone().0);
-                        match storage_data {
-                            Some(storage) => results.push((hash, storage)),
-                            None => {}
-                        }
-                    }
-
-                    let _ = resp.send(Ok(results));
+                    let _ = resp.send(query_prefix(&api, storagekey, at));
+                }
+                Command::QueryNMap {
+                    storage_prefix,
+                    storage_key_name,
+                    keys,
+                    at,
+                    resp,
+                } => {
+                    let result = resolve_block(&api, at).and_then(|at| {
+                        let storagekey =
+                            storage_n_map_key(&api, storage_prefix, storage_key_name, &keys, true)?;
+                        api.get_opaque_storage_by_key(storagekey, at)
+                            .map_err(|e| anyhow::anyhow!("{:?}", e))
+                    });
+                    let _ = resp.send(result);
+                }
+                Command::QueryNMapPrefix {
+                    storage_prefix,
+                    storage_key_name,
+                    keys,
+                    at,
+                    resp,
+                } => {
+                    let result = resolve_block(&api, at).and_then(|at| {
+                        let storagekey =
+                            storage_n_map_key(&api, storage_prefix, storage_key_name, &keys, false)?;
+                        query_prefix(&api, storagekey, at)
+                    });
+                    let _ = resp.send(result);
+                }
+                // 一次 RPC 请求查询多个存储项
+                Command::QueryStorageMany { queries, at, resp } => {
+                    let result = resolve_block(&api, at).and_then(|at| {
+                        let keys = queries
+                            .iter()
+                            .map(|q| storage_query_key(&api, q))
+                            .collect::<anyhow::Result<Vec<StorageKey>>>()?;
+                        query_storage_at(&api, keys, at)
+                    });
+                    let _ = resp.send(result);
+                }
+                // 本地计算存储键
+                Command::QueryStorageKey { query, resp } => {
+                    let _ = resp.send(storage_query_key(&api, &query).map(|k| k.0));
+                }
+                // 获取存储读取证明
+                Command::QueryStorageProof { query, at, resp } => {
+                    let result = resolve_block(&api, at)
+                        .and_then(|at| query_storage_proof(&api, &query, at));
+                    let _ = resp.send(result);
                 }
                 Command::SubmitExtrinsic { resp, call, signer } => {
                     let from_pair = account::get_from_address(signer.clone()).unwrap();
@@ -290,7 +346,7 @@ impl Client {
                                 "[+] Extrinsic got included in block {:?}",
                                 report.block_hash
                             );
-                            let _ = resp.send(Ok(report.block_hash.unwrap().to_string()));
+                            let _ = resp.send(Ok(report.block_hash.unwrap()));
                         }
                         Err(e) => {
                             println!("[+] Couldn't execute the extrinsic due to {:?}\n", e);
@@ -310,17 +366,19 @@ impl Client {
         &self,
         storage_prefix: &'static str,
         storage_key_name: &'static str,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Option<V>> {
         let sender = self.get_sender()?;
         let (resp_tx, resp_rx) = oneshot::channel();
         let cmd = Command::QueryValue {
             storage_prefix,
             storage_key_name,
+            at,
             resp: resp_tx,
         };
         sender.send(cmd).await.unwrap();
 
-        let s = resp_rx.await.unwrap().unwrap();
+        let s = resp_rx.await.unwrap()?;
         match s {
             Some(storage) => Ok(Some(Decode::decode(&mut storage.as_slice())?)),
             None => Ok(None),
@@ -331,17 +389,19 @@ impl Client {
         &self,
         storage_prefix: &'static str,
         storage_key_name: &'static str,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Vec<(String, V)>> {
         let sender = self.get_sender()?;
         let (resp_tx, resp_rx) = oneshot::channel();
         let cmd = Command::QueryMapAll {
             storage_prefix,
             storage_key_name,
+            at,
             resp: resp_tx,
         };
         sender.send(cmd).await.unwrap();
 
-        let s = resp_rx.await.unwrap().unwrap();
+        let s = resp_rx.await.unwrap()?;
         let mut results = vec![];
         for storage in s.iter() {
             results.push((
@@ -357,6 +417,7 @@ impl Client {
         storage_prefix: &'static str,
         storage_key_name: &'static str,
         key: QueryKey,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Option<V>> {
         let sender = self.get_sender()?;
         let (resp_tx, resp_rx) = oneshot::channel();
@@ -364,11 +425,12 @@ impl Client {
             storage_prefix,
             storage_key_name,
             key,
+            at,
             resp: resp_tx,
         };
         sender.send(cmd).await.unwrap();
 
-        let s = resp_rx.await.unwrap().unwrap();
+        let s = resp_rx.await.unwrap()?;
         match s {
             Some(storage) => Ok(Some(Decode::decode(&mut storage.as_slice())?)),
             None => Ok(None),
@@ -381,6 +443,7 @@ impl Client {
         storage_key_name: &'static str,
         first: QueryKey,
         second: QueryKey,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Option<V>> {
         let sender = self.get_sender()?;
         let (resp_tx, resp_rx) = oneshot::channel();
@@ -389,11 +452,12 @@ impl Client {
             storage_key_name,
             first,
             second,
+            at,
             resp: resp_tx,
         };
         sender.send(cmd).await.unwrap();
 
-        let s = resp_rx.await.unwrap().unwrap();
+        let s = resp_rx.await.unwrap()?;
         match s {
             Some(storage) => Ok(Some(Decode::decode(&mut storage.as_slice())?)),
             None => Ok(None),
@@ -405,6 +469,7 @@ impl Client {
         storage_prefix: &'static str,
         storage_key_name: &'static str,
         first: QueryKey,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Vec<(String, V)>> {
         let sender = self.get_sender()?;
         let (resp_tx, resp_rx) = oneshot::channel();
@@ -412,11 +477,67 @@ impl Client {
             storage_prefix,
             storage_key_name,
             first,
+            at,
             resp: resp_tx,
         };
         sender.send(cmd).await.unwrap();
 
-        let s = resp_rx.await.unwrap().unwrap();
+        let s = resp_rx.await.unwrap()?;
+        let mut results = vec![];
+        for storage in s.iter() {
+            results.push((
+                storage.0.clone(),
+                Decode::decode(&mut storage.1.as_slice())?,
+            ));
+        }
+        Ok(results)
+    }
+
+    pub async fn get_storage_n_map<V: Decode>(
+        &self,
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+        keys: Vec<QueryKey>,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Option<V>> {
+        let sender = self.get_sender()?;
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = Command::QueryNMap {
+            storage_prefix,
+            storage_key_name,
+            keys,
+            at,
+            resp: resp_tx,
+        };
+        sender.send(cmd).await.unwrap();
+
+        let s = resp_rx.await.unwrap()?;
+        match s {
+            Some(storage) => Ok(Some(Decode::decode(&mut storage.as_slice())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 按 key 前缀查询 map 中的所有值，keys 可以少于 map 的 key 数量
+    pub async fn get_storage_n_map_prefix<V: Decode>(
+        &self,
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+        keys: Vec<QueryKey>,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Vec<(String, V)>> {
+        let sender = self.get_sender()?;
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = Command::QueryNMapPrefix {
+            storage_prefix,
+            storage_key_name,
+            keys,
+            at,
+            resp: resp_tx,
+        };
+        sender.send(cmd).await.unwrap();
+
+        let s = resp_rx.await.unwrap()?;
         let mut results = vec![];
         for storage in s.iter() {
             results.push((
@@ -427,7 +548,115 @@ impl Client {
         Ok(results)
     }
 
+    /// 在一次 RPC 请求中查询多个存储项，返回值顺序与 queries 一致
+    pub async fn get_storage_many(
+        &self,
+        queries: Vec<StorageQuery>,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Vec<Option<Vec<u8>>>> {
+        let sender = self.get_sender()?;
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = Command::QueryStorageMany {
+            queries,
+            at,
+            resp: resp_tx,
+        };
+        sender.send(cmd).await.unwrap();
+
+        resp_rx.await.unwrap()
+    }
+
+    /// 使用本地元数据计算存储键，用于校验读取证明
+    pub async fn get_storage_key(&self, query: StorageQuery) -> anyhow::Result<Vec<u8>> {
+        let sender = self.get_sender()?;
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = Command::QueryStorageKey {
+            query,
+            resp: resp_tx,
+        };
+        sender.send(cmd).await.unwrap();
+
+        resp_rx.await.unwrap()
+    }
+
+    /// 获取存储项的读取证明，at 为空时使用最新确认的区块
+    /// 使用 proof::verify_and_decode 和 get_storage_key 计算的 key 校验
+    pub async fn get_storage_with_proof(
+        &self,
+        query: StorageQuery,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<StorageReadProof> {
+        let sender = self.get_sender()?;
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = Command::QueryStorageProof {
+            query,
+            at,
+            resp: resp_tx,
+        };
+        sender.send(cmd).await.unwrap();
+
+        resp_rx.await.unwrap()
+    }
+
+    /// 订阅存储项的变化，每次变化推送解码后的值和所在区块
+    /// 订阅使用独立的连接，丢弃返回的 stream 后在下一次变化时退订
+    pub async fn watch_storage<V: Decode + Send + 'static>(
+        &self,
+        query: StorageQuery,
+    ) -> anyhow::Result<StorageStream<V>> {
+        let url = self.get_url()?;
+        let (tx, rx) = mpsc::unbounded();
+        let (ready_tx, ready_rx) = oneshot::channel::<anyhow::Result<()>>();
+
+        tokio::task::spawn_blocking(move || {
+            let subscription = JsonrpseeClient::new(url.as_str())
+                .map_err(|e| anyhow::anyhow!("{:?}", e))
+                .and_then(|client| {
+                    Api::<WeteeConfig, JsonrpseeClient>::new(client)
+                        .map_err(|e| anyhow::anyhow!("{:?}", e))
+                })
+                .and_then(|api| {
+                    let key = storage_query_key(&api, &query)?;
+                    api.client()
+                        .subscribe::<StorageChangeSet<Hash>>(
+                            "state_subscribeStorage",
+                            rpc_params![vec![key]],
+                            "state_unsubscribeStorage",
+                        )
+                        .map_err(|e| anyhow::anyhow!("{:?}", e))
+                });
+            let mut subscription = match subscription {
+                Ok(s) => {
+                    let _ = ready_tx.send(Ok(()));
+                    s
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            while let Some(change_set) = subscription.next() {
+                let change = change_set
+                    .map_err(|e| anyhow::anyhow!("{:?}", e))
+                    .and_then(decode_change::<V>);
+                if tx.unbounded_send(change).is_err() {
+                    break;
+                }
+            }
+            let _ = subscription.unsubscribe();
+        });
+
+        ready_rx.await.unwrap()?;
+        Ok(rx)
+    }
+
     pub async fn send_and_sign(&self, call: RuntimeCall, signer: String) -> anyhow::Result<()> {
+        self.send_and_sign_in_block(call, signer).await.map(|_| ())
+    }
+
+    /// 发送交易，返回交易所在区块
+    pub async fn send_and_sign_in_block(&self, call: RuntimeCall, signer: String) -> anyhow::Result<Hash> {
         let sender = self.get_sender()?;
         let (resp_tx, resp_rx) = oneshot::channel();
         let cmd = Command::SubmitExtrinsic {
@@ -437,8 +666,7 @@ impl Client {
         };
         sender.send(cmd).await.unwrap();
 
-        let _ = resp_rx.await.unwrap().unwrap();
-        Ok(())
+        resp_rx.await.unwrap()
     }
 
     pub fn get_sender(&self) -> anyhow::Result<Sender<Command>> {
@@ -495,12 +723,324 @@ impl Client {
     }
 }
 
+// 将区块引用转换为区块哈希
+fn resolve_block(api: &ChainApi, at: Option<BlockRef>) -> anyhow::Result<Option<Hash>> {
+    match at {
+        None => Ok(None),
+        Some(BlockRef::Hash(hash)) => Ok(Some(hash)),
+        Some(BlockRef::Number(number)) => {
+            let hash = api
+                .get_block_hash(Some(number))
+                .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+            match hash {
+                Some(hash) => Ok(Some(hash)),
+                None => Err(anyhow::anyhow!("block {} not found", number)),
+            }
+        }
+    }
+}
+
+// 每次读取的存储键数量
+const KEYS_PAGE_SIZE: u32 = 1000;
+
+// 查询前缀下的所有值，按 KEYS_PAGE_SIZE 分页读取存储键，每个值一次请求
+fn query_prefix(
+    api: &ChainApi,
+    storagekey: StorageKey,
+    at: Option<Hash>,
+) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    let mut storage_keys: Vec<StorageKey> = vec![];
+    loop {
+        // 从上一页的最后一个键之后继续
+        let start_key = storage_keys.last().cloned();
+        let page = api
+            .get_storage_keys_paged(Some(storagekey.clone()), KEYS_PAGE_SIZE, start_key, at)
+            .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        let done = page.len() < KEYS_PAGE_SIZE as usize;
+        storage_keys.extend(page);
+        if done {
+            break;
+        }
+    }
+
+    let mut results = vec![];
+    for storage_key in storage_keys.iter() {
+        let storage_data: Option<Vec<u8>> = api
+            .get_opaque_storage_by_key(storage_key.clone(), at)
+            .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        let hash = "0x".to_owned() + &hex::encode(storage_key.clone().0);
+        match storage_data {
+            Some(storage) => results.push((hash, storage)),
+            None => {}
+        }
+    }
+
+    Ok(results)
+}
+
+// 根据元数据中的 hasher 计算任意数量 key 的存储键
+// full 为 true 时要求 key 数量与 map 完全一致
+pub fn storage_n_map_key(
+    api: &ChainApi,
+    storage_prefix: &'static str,
+    storage_key_name: &'static str,
+    keys: &[QueryKey],
+    full: bool,
+) -> anyhow::Result<StorageKey> {
+    let entry = api
+        .metadata()
+        .pallet(storage_prefix)
+        .and_then(|p| p.storage(storage_key_name))
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    let hashers = match &entry.ty {
+        StorageEntryType::Map { hashers, .. } => hashers,
+        StorageEntryType::Plain(_) => {
+            return Err(anyhow::anyhow!(
+                "{}::{} is not a map",
+                storage_prefix,
+                storage_key_name
+            ))
+        }
+    };
+    if keys.len() > hashers.len() || (full && keys.len() != hashers.len()) {
+        return Err(anyhow::anyhow!(
+            "{}::{} expects {} keys, got {}",
+            storage_prefix,
+            storage_key_name,
+            hashers.len(),
+            keys.len()
+        ));
+    }
+
+    let mut bytes = twox_128(storage_prefix.as_bytes()).to_vec();
+    bytes.extend(&twox_128(storage_key_name.as_bytes())[..]);
+    for (key, hasher) in keys.iter().zip(hashers.iter()) {
+        bytes.extend(hash_key(&key.encode(), hasher));
+    }
+    Ok(StorageKey(bytes))
+}
+
+// 计算批量查询项的存储键
+fn storage_query_key(api: &ChainApi, query: &StorageQuery) -> anyhow::Result<StorageKey> {
+    let key = match query {
+        StorageQuery::Value {
+            storage_prefix,
+            storage_key_name,
+        } => api
+            .metadata()
+            .storage_value_key(*storage_prefix, *storage_key_name),
+        StorageQuery::Map {
+            storage_prefix,
+            storage_key_name,
+            key,
+        } => api
+            .metadata()
+            .storage_map_key(*storage_prefix, *storage_key_name, key.clone()),
+        StorageQuery::DoubleMap {
+            storage_prefix,
+            storage_key_name,
+            first,
+            second,
+        } => api.metadata().storage_double_map_key(
+            *storage_prefix,
+            *storage_key_name,
+            first.clone(),
+            second.clone(),
+        ),
+        StorageQuery::NMap {
+            storage_prefix,
+            storage_key_name,
+            keys,
+        } => return storage_n_map_key(api, *storage_prefix, *storage_key_name, keys, true),
+    };
+    key.map_err(|e| anyhow::anyhow!("{:?}", e))
+}
+
+// 通过 state_queryStorageAt 一次性读取多个存储键
+fn query_storage_at(
+    api: &ChainApi,
+    keys: Vec<StorageKey>,
+    at: Option<Hash>,
+) -> anyhow::Result<Vec<Option<Vec<u8>>>> {
+    if keys.is_empty() {
+        return Ok(vec![]);
+    }
+    let change_sets: Vec<StorageChangeSet<Hash>> = api
+        .client()
+        .request("state_queryStorageAt", rpc_params![keys.clone(), at])
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+
+    let mut values = HashMap::new();
+    for change_set in change_sets.into_iter() {
+        for (key, data) in change_set.changes.into_iter() {
+            values.insert(key, data.map(|d| d.0));
+        }
+    }
+
+    Ok(keys
+        .iter()
+        .map(|k| values.get(k).cloned().unwrap_or(None))
+        .collect())
+}
+
+// state_getRuntimeVersion 返回值中需要的字段
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RuntimeVersionInfo {
+    spec_version: u32,
+}
+
+// 获取指定区块的元数据
+fn query_metadata_at(api: &ChainApi, hash: Hash) -> anyhow::Result<Metadata> {
+    let prefixed = query_metadata_prefixed(api, Some(hash))?;
+    Metadata::try_from(prefixed).map_err(|e| anyhow::anyhow!("{:?}", e))
+}
+
+// 获取 v14 格式的原始元数据，包含类型信息
+fn query_runtime_metadata(api: &ChainApi, hash: Option<Hash>) -> anyhow::Result<RuntimeMetadataV14> {
+    match query_metadata_prefixed(api, hash)?.1 {
+        RuntimeMetadata::V14(metadata) => Ok(metadata),
+        _ => Err(anyhow::anyhow!("unsupported metadata version")),
+    }
+}
+
+fn query_metadata_prefixed(api: &ChainApi, hash: Option<Hash>) -> anyhow::Result<RuntimeMetadataPrefixed> {
+    let bytes: Bytes = api
+        .client()
+        .request("state_getMetadata", rpc_params![hash])
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    Ok(RuntimeMetadataPrefixed::decode(&mut bytes.0.as_slice())?)
+}
+
+// 获取区块原始数据
+fn query_block(api: &ChainApi, number: BlockNumber) -> anyhow::Result<Option<ChainBlock>> {
+    let hash = match api
+        .get_block_hash(Some(number))
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?
+    {
+        Some(hash) => hash,
+        None => return Ok(None),
+    };
+    let block = match api
+        .get_block(Some(hash))
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?
+    {
+        Some(block) => block,
+        None => return Ok(None),
+    };
+
+    let events_key = api
+        .metadata()
+        .storage_value_key("System", "Events")
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    let events = api
+        .get_opaque_storage_by_key(events_key, Some(hash))
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?
+        .unwrap_or_default();
+
+    let timestamp_key = api
+        .metadata()
+        .storage_value_key("Timestamp", "Now")
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    let timestamp = match api
+        .get_opaque_storage_by_key(timestamp_key, Some(hash))
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?
+    {
+        Some(storage) => Decode::decode(&mut storage.as_slice())?,
+        None => 0,
+    };
+
+    let version: RuntimeVersionInfo = api
+        .client()
+        .request("state_getRuntimeVersion", rpc_params![Some(hash)])
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+
+    Ok(Some(ChainBlock {
+        number,
+        hash,
+        parent_hash: block.header.parent_hash,
+        timestamp,
+        spec_version: version.spec_version,
+        extrinsics: block.extrinsics.iter().map(|xt| xt.encode()).collect(),
+        events,
+    }))
+}
+
+// 通过 state_getReadProof 获取存储证明和对应区块的状态根
+fn query_storage_proof(
+    api: &ChainApi,
+    query: &StorageQuery,
+    at: Option<Hash>,
+) -> anyhow::Result<StorageReadProof> {
+    let at = match at {
+        Some(hash) => hash,
+        None => api
+            .get_finalized_head()
+            .map_err(|e| anyhow::anyhow!("{:?}", e))?
+            .ok_or_else(|| anyhow::anyhow!("finalized head not found"))?,
+    };
+    let key = storage_query_key(api, query)?;
+    let header = api
+        .get_header(Some(at))
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?
+        .ok_or_else(|| anyhow::anyhow!("block {:?} not found", at))?;
+    let read_proof = api
+        .get_storage_proof_by_keys(vec![key.clone()], Some(at))
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?
+        .ok_or_else(|| anyhow::anyhow!("no read proof at {:?}", at))?;
+
+    Ok(StorageReadProof {
+        at,
+        state_root: header.state_root,
+        key: key.0,
+        proof: read_proof.proof.into_iter().map(|p| p.0).collect(),
+    })
+}
+
+// 解码单个存储项的变化
+fn decode_change<V: Decode>(change_set: StorageChangeSet<Hash>) -> anyhow::Result<StorageChange<V>> {
+    let value = match change_set.changes.into_iter().next() {
+        Some((_, Some(data))) => Some(Decode::decode(&mut data.0.as_slice())?),
+        _ => None,
+    };
+    Ok(StorageChange {
+        block: change_set.block,
+        value,
+    })
+}
+
+/// 从 hex 存储键的末尾解码 key
+/// 仅适用于 Identity、Twox64Concat、Blake2_128Concat 等保留原始 key 的 hasher
+pub fn storage_key_suffix<K: Decode>(key: &str, len: usize) -> anyhow::Result<K> {
+    let bytes = hex::decode(key.trim_start_matches("0x"))?;
+    if bytes.len() < len {
+        return Err(anyhow::anyhow!("storage key {} is too short", key));
+    }
+    Ok(Decode::decode(&mut &bytes[bytes.len() - len..])?)
+}
+
+// 按 hasher 计算单个 key
+fn hash_key(encoded: &[u8], hasher: &StorageHasher) -> Vec<u8> {
+    match hasher {
+        StorageHasher::Identity => encoded.to_vec(),
+        StorageHasher::Blake2_128 => blake2_128(encoded).to_vec(),
+        StorageHasher::Blake2_128Concat => [blake2_128(encoded).as_slice(), encoded].concat(),
+        StorageHasher::Blake2_256 => blake2_256(encoded).to_vec(),
+        StorageHasher::Twox128 => twox_128(encoded).to_vec(),
+        StorageHasher::Twox256 => twox_256(encoded).to_vec(),
+        StorageHasher::Twox64Concat => [twox_64(encoded).as_slice(), encoded].concat(),
+    }
+}
+
 // 全局区块链连接
 pub static WORKER_POOL: Lazy<Mutex<Vec<(String, Option<Sender<Command>>)>>> =
     Lazy::new(|| Mutex::new(vec![]));
 // 全局区块链状态
 pub static WORKER_STATUS: Lazy<Mutex<Vec<(u8, usize)>>> = Lazy::new(|| Mutex::new(vec![]));
 
+// 每个客户端的出块时间 (毫秒)
+static BLOCK_TIMES: Lazy<Mutex<HashMap<usize, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
 // 获取区块链连接
 pub fn init_worker_send(url: String) -> anyhow::Result<usize, anyhow::Error> {
     // 连接区块链
@@ -0,0 +1,583 @@
+use std::collections::HashMap;
+
+use codec::{Compact, Decode, Encode};
+use frame_metadata::v14::RuntimeMetadataV14;
+use scale_info::{form::PortableForm, Field, TypeDef, TypeDefPrimitive};
+use serde::Serialize;
+use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+use wetee_runtime::RuntimeCall;
+
+use crate::chain::DECIMALS;
+use crate::model::amount::Amount;
+
+/// 交易参数值
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum ArgValue {
+    // 嵌套的调用，如 sudo、batch、proxy
+    Call(Box<CallNode>),
+    // SS58 地址
+    Address(String),
+    // 带精度的代币数量
+    Amount { raw: String, decimals: u8, display: String },
+    Number(String),
+    Bool(bool),
+    Text(String),
+    Bytes(String),
+    None,
+    List(Vec<ArgValue>),
+    Struct(Vec<Arg>),
+    Variant { name: String, fields: Vec<Arg> },
+}
+
+/// 命名参数，元组结构体的参数没有名称
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Arg {
+    pub name: Option<String>,
+    pub type_name: Option<String>,
+    pub value: ArgValue,
+}
+
+/// 调用描述
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CallNode {
+    pub pallet: String,
+    pub call: String,
+    pub args: Vec<Arg>,
+}
+
+impl CallNode {
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// 按名称读取参数
+    pub fn arg(&self, name: &str) -> Option<&ArgValue> {
+        self.args
+            .iter()
+            .find(|a| a.name.as_deref() == Some(name))
+            .map(|a| &a.value)
+    }
+
+    /// 按名称读取整数参数
+    pub fn number_arg(&self, name: &str) -> Option<u128> {
+        match self.arg(name)? {
+            ArgValue::Number(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// 数量为 DAO 代币的模块、其中的 DAO 代币 ID 字段和 DAO 代币数量字段，数量字段为空时模块中所有数量都是 DAO 代币
+/// WeteeProject 的任务奖励和项目资金都是 DAO 代币，WeteeGov 中只有投票质押是 DAO 代币
+const DAO_ASSET_FIELDS: [(&str, &str, &[&str]); 4] = [
+    ("WeteeAsset", "dao_id", &[]),
+    ("WeteeProject", "dao_id", &[]),
+    ("Tokens", "currency_id", &[]),
+    ("WeteeGov", "dao_id", &["pledge"]),
+];
+
+/// DAO 代币模块中数量为原生代币的字段，加入 DAO 的押金为原生代币
+const NATIVE_FIELDS: [&str; 1] = ["existenial_deposit"];
+
+/// 根据元数据的类型信息把 RuntimeCall 解析为结构化的调用树
+pub struct CallDescriber {
+    metadata: RuntimeMetadataV14,
+    call_ty: u32,
+    // 地址格式
+    pub ss58_format: u16,
+    // 原生代币精度
+    pub decimals: u8,
+    // DAO 代币精度，DAO_ASSET_FIELDS 中的字段按 DAO 代币 ID 使用，精度未知时数量显示为原始整数
+    pub asset_decimals: HashMap<u64, u8>,
+}
+
+impl CallDescriber {
+    /// 元数据可以通过 Client::get_runtime_metadata 获取
+    pub fn new(metadata: RuntimeMetadataV14) -> anyhow::Result<Self> {
+        let extrinsic = metadata
+            .types
+            .resolve(metadata.extrinsic.ty.id)
+            .ok_or_else(|| anyhow::anyhow!("extrinsic type not found"))?;
+        let call_ty = extrinsic
+            .type_params
+            .iter()
+            .find(|p| p.name == "Call")
+            .and_then(|p| p.ty)
+            .ok_or_else(|| anyhow::anyhow!("call type not found in metadata"))?
+            .id;
+
+        Ok(Self {
+            metadata,
+            call_ty,
+            ss58_format: 42,
+            decimals: DECIMALS,
+            asset_decimals: HashMap::new(),
+        })
+    }
+
+    pub fn with_asset_decimals(mut self, dao_id: u64, decimals: u8) -> Self {
+        self.asset_decimals.insert(dao_id, decimals);
+        self
+    }
+
+    pub fn describe(&self, call: &RuntimeCall) -> anyhow::Result<CallNode> {
+        self.describe_encoded(&call.encode())
+    }
+
+    pub fn describe_json(&self, call: &RuntimeCall) -> anyhow::Result<String> {
+        self.describe(call)?.to_json()
+    }
+
+    /// 解析 SCALE 编码的调用
+    pub fn describe_encoded(&self, data: &[u8]) -> anyhow::Result<CallNode> {
+        let input = &mut &data[..];
+        let node = self.decode_call(input)?;
+        if !input.is_empty() {
+            return Err(anyhow::anyhow!("{} bytes left after decoding call", input.len()));
+        }
+        Ok(node)
+    }
+
+    /// 解析 SCALE 编码的事件，不含 phase 和 topics
+    /// 事件的 call 为事件名
+    pub fn describe_event(&self, data: &[u8]) -> anyhow::Result<CallNode> {
+        let input = &mut &data[..];
+        let pallet_index = u8::decode(input)?;
+        let pallet = self
+            .metadata
+            .pallets
+            .iter()
+            .find(|p| p.index == pallet_index)
+            .ok_or_else(|| anyhow::anyhow!("pallet {} not found", pallet_index))?;
+        let events_ty = pallet
+            .event
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("pallet {} has no events", pallet.name))?
+            .ty
+            .id;
+
+        let event_index = u8::decode(input)?;
+        let variant = match &self.resolve(events_ty)?.type_def {
+            TypeDef::Variant(v) => v.variants.iter().find(|v| v.index == event_index),
+            _ => None,
+        }
+        .ok_or_else(|| anyhow::anyhow!("event {} of {} not found", event_index, pallet.name))?;
+        let args = self.decode_args(&pallet.name, &variant.fields, input)?;
+        if !input.is_empty() {
+            return Err(anyhow::anyhow!("{} bytes left after decoding event", input.len()));
+        }
+
+        Ok(CallNode {
+            pallet: pallet.name.clone(),
+            call: variant.name.clone(),
+            args,
+        })
+    }
+
+    fn decode_call(&self, input: &mut &[u8]) -> anyhow::Result<CallNode> {
+        let pallet_index = u8::decode(input)?;
+        let pallet = self
+            .metadata
+            .pallets
+            .iter()
+            .find(|p| p.index == pallet_index)
+            .ok_or_else(|| anyhow::anyhow!("pallet {} not found", pallet_index))?;
+        let calls_ty = pallet
+            .calls
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("pallet {} has no calls", pallet.name))?
+            .ty
+            .id;
+
+        let call_index = u8::decode(input)?;
+        let variant = match &self.resolve(calls_ty)?.type_def {
+            TypeDef::Variant(v) => v.variants.iter().find(|v| v.index == call_index),
+            _ => None,
+        }
+        .ok_or_else(|| anyhow::anyhow!("call {} of {} not found", call_index, pallet.name))?;
+
+        let args = self.decode_args(&pallet.name, &variant.fields, input)?;
+
+        Ok(CallNode {
+            pallet: pallet.name.clone(),
+            call: variant.name.clone(),
+            args,
+        })
+    }
+
+    // DAO 代币数量使用 DAO 代币 ID 对应的精度，ID 之前或精度未知时显示为原始整数
+    fn decode_args(
+        &self,
+        pallet: &str,
+        fields: &[Field<PortableForm>],
+        input: &mut &[u8],
+    ) -> anyhow::Result<Vec<Arg>> {
+        let asset = DAO_ASSET_FIELDS.iter().find(|(p, _, _)| *p == pallet);
+        let mut asset_decimals = None;
+        let mut args = vec![];
+        for field in fields.iter() {
+            let name = field.name.as_deref().unwrap_or_default();
+            let is_asset = match asset {
+                Some((_, _, amounts)) => {
+                    !NATIVE_FIELDS.contains(&name) && (amounts.is_empty() || amounts.contains(&name))
+                }
+                None => false,
+            };
+            let decimals = if is_asset { asset_decimals } else { Some(self.decimals) };
+            let value = self.decode_field(field, decimals, input)?;
+            if let (Some((_, id_field, _)), ArgValue::Number(n)) = (asset, &value) {
+                if name == *id_field {
+                    asset_decimals = n
+                        .parse()
+                        .ok()
+                        .and_then(|id: u64| self.asset_decimals.get(&id))
+                        .copied();
+                }
+            }
+            args.push(Arg {
+                name: field.name.clone(),
+                type_name: field.type_name.clone(),
+                value,
+            });
+        }
+        Ok(args)
+    }
+
+    fn decode_field(
+        &self,
+        field: &Field<PortableForm>,
+        decimals: Option<u8>,
+        input: &mut &[u8],
+    ) -> anyhow::Result<ArgValue> {
+        let is_balance = field
+            .type_name
+            .as_deref()
+            .map_or(false, |n| n.contains("Balance"));
+        self.decode_value(field.ty.id, decimals.filter(|_| is_balance), decimals, input)
+    }
+
+    fn decode_fields(
+        &self,
+        fields: &[Field<PortableForm>],
+        decimals: Option<u8>,
+        input: &mut &[u8],
+    ) -> anyhow::Result<Vec<Arg>> {
+        fields
+            .iter()
+            .map(|field| {
+                Ok(Arg {
+                    name: field.name.clone(),
+                    type_name: field.type_name.clone(),
+                    value: self.decode_field(field, decimals, input)?,
+                })
+            })
+            .collect()
+    }
+
+    // amount 不为 None 时数值按代币数量显示，decimals 为嵌套字段中数量的精度
+    fn decode_value(
+        &self,
+        ty: u32,
+        amount: Option<u8>,
+        decimals: Option<u8>,
+        input: &mut &[u8],
+    ) -> anyhow::Result<ArgValue> {
+        if ty == self.call_ty {
+            return Ok(ArgValue::Call(Box::new(self.decode_call(input)?)));
+        }
+
+        let t = self.resolve(ty)?;
+        if t.path.segments.last().map(|s| s.as_str()) == Some("AccountId32") {
+            let raw = <[u8; 32]>::decode(input)?;
+            return Ok(ArgValue::Address(
+                AccountId32::from(raw)
+                    .to_ss58check_with_version(Ss58AddressFormat::custom(self.ss58_format)),
+            ));
+        }
+
+        Ok(match &t.type_def {
+            TypeDef::Composite(c) => {
+                // 单字段的包装类型直接展开
+                if c.fields.len() == 1 && c.fields[0].name.is_none() {
+                    self.decode_value(c.fields[0].ty.id, amount, decimals, input)?
+                } else {
+                    ArgValue::Struct(self.decode_fields(&c.fields, decimals, input)?)
+                }
+            }
+            TypeDef::Variant(v) => {
+                let index = u8::decode(input)?;
+                let variant = v
+                    .variants
+                    .iter()
+                    .find(|v| v.index == index)
+                    .ok_or_else(|| anyhow::anyhow!("variant {} of type {} not found", index, ty))?;
+                let is_option = t.path.segments == ["Option"];
+                if is_option && variant.name == "None" {
+                    ArgValue::None
+                } else if is_option {
+                    self.decode_value(variant.fields[0].ty.id, amount, decimals, input)?
+                } else if t.path.segments.last().map(|s| s.as_str()) == Some("MultiAddress")
+                    && variant.name == "Id"
+                {
+                    self.decode_value(variant.fields[0].ty.id, None, decimals, input)?
+                } else {
+                    ArgValue::Variant {
+                        name: variant.name.clone(),
+                        fields: self.decode_fields(&variant.fields, decimals, input)?,
+                    }
+                }
+            }
+            TypeDef::Sequence(s) => {
+                let len = Compact::<u32>::decode(input)?.0 as usize;
+                if self.is_u8(s.type_param.id) {
+                    bytes_value(read_bytes(input, len)?)
+                } else {
+                    let mut items = Vec::with_capacity(len.min(1024));
+                    for _ in 0..len {
+                        items.push(self.decode_value(s.type_param.id, amount, decimals, input)?);
+                    }
+                    ArgValue::List(items)
+                }
+            }
+            TypeDef::Array(a) => {
+                if self.is_u8(a.type_param.id) {
+                    ArgValue::Bytes(format!("0x{}", hex::encode(read_bytes(input, a.len as usize)?)))
+                } else {
+                    let mut items = vec![];
+                    for _ in 0..a.len {
+                        items.push(self.decode_value(a.type_param.id, amount, decimals, input)?);
+                    }
+                    ArgValue::List(items)
+                }
+            }
+            TypeDef::Tuple(t) => {
+                let mut items = vec![];
+                for f in t.fields.iter() {
+                    items.push(self.decode_value(f.id, None, decimals, input)?);
+                }
+                ArgValue::List(items)
+            }
+            TypeDef::Primitive(p) => decode_primitive(p, amount, input)?,
+            TypeDef::Compact(_) => number_value(Compact::<u128>::decode(input)?.0, amount),
+            TypeDef::BitSequence(_) => {
+                return Err(anyhow::anyhow!("bit sequence arguments are not supported"))
+            }
+        })
+    }
+
+    fn resolve(&self, ty: u32) -> anyhow::Result<&scale_info::Type<PortableForm>> {
+        self.metadata
+            .types
+            .resolve(ty)
+            .ok_or_else(|| anyhow::anyhow!("type {} not found in metadata", ty))
+    }
+
+    fn is_u8(&self, ty: u32) -> bool {
+        matches!(
+            self.resolve(ty).map(|t| &t.type_def),
+            Ok(TypeDef::Primitive(TypeDefPrimitive::U8))
+        )
+    }
+}
+
+fn number_value(n: u128, amount: Option<u8>) -> ArgValue {
+    match amount {
+        Some(decimals) => ArgValue::Amount {
+            raw: n.to_string(),
+            decimals,
+            display: Amount::from_raw(n, decimals).to_string(),
+        },
+        None => ArgValue::Number(n.to_string()),
+    }
+}
+
+// 可读的字节显示为文本
+fn bytes_value(bytes: Vec<u8>) -> ArgValue {
+    match String::from_utf8(bytes) {
+        Ok(s) if !s.chars().any(|c| c.is_control()) => ArgValue::Text(s),
+        Ok(s) => ArgValue::Bytes(format!("0x{}", hex::encode(s.as_bytes()))),
+        Err(e) => ArgValue::Bytes(format!("0x{}", hex::encode(e.as_bytes()))),
+    }
+}
+
+fn read_bytes(input: &mut &[u8], len: usize) -> anyhow::Result<Vec<u8>> {
+    if input.len() < len {
+        return Err(anyhow::anyhow!("unexpected end of input"));
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes.to_vec())
+}
+
+fn decode_primitive(
+    p: &TypeDefPrimitive,
+    amount: Option<u8>,
+    input: &mut &[u8],
+) -> anyhow::Result<ArgValue> {
+    Ok(match p {
+        TypeDefPrimitive::Bool => ArgValue::Bool(bool::decode(input)?),
+        TypeDefPrimitive::Char => {
+            let c = u32::decode(input)?;
+            ArgValue::Text(char::from_u32(c).map(String::from).unwrap_or_default())
+        }
+        TypeDefPrimitive::Str => ArgValue::Text(String::decode(input)?),
+        TypeDefPrimitive::U8 => number_value(u8::decode(input)? as u128, amount),
+        TypeDefPrimitive::U16 => number_value(u16::decode(input)? as u128, amount),
+        TypeDefPrimitive::U32 => number_value(u32::decode(input)? as u128, amount),
+        TypeDefPrimitive::U64 => number_value(u64::decode(input)? as u128, amount),
+        TypeDefPrimitive::U128 => number_value(u128::decode(input)?, amount),
+        TypeDefPrimitive::I8 => ArgValue::Number(i8::decode(input)?.to_string()),
+        TypeDefPrimitive::I16 => ArgValue::Number(i16::decode(input)?.to_string()),
+        TypeDefPrimitive::I32 => ArgValue::Number(i32::decode(input)?.to_string()),
+        TypeDefPrimitive::I64 => ArgValue::Number(i64::decode(input)?.to_string()),
+        TypeDefPrimitive::I128 => ArgValue::Number(i128::decode(input)?.to_string()),
+        TypeDefPrimitive::U256 | TypeDefPrimitive::I256 => {
+            ArgValue::Bytes(format!("0x{}", hex::encode(read_bytes(input, 32)?)))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed};
+    use sp_runtime::MultiAddress;
+    use wetee_gov::Opinion;
+    use wetee_runtime::{
+        AccountId, Pledge, Runtime, RuntimeEvent, WeteeAssetsCall, WeteeGovCall, WeteeProjectCall,
+        WeteeSudoCall,
+    };
+
+    use super::*;
+
+    const DAO_ID: u64 = 5000;
+
+    // 使用 SDK 编译时的 runtime 元数据
+    fn describer() -> CallDescriber {
+        let bytes = Runtime::metadata().encode();
+        let metadata = match RuntimeMetadataPrefixed::decode(&mut bytes.as_slice()).unwrap().1 {
+            RuntimeMetadata::V14(metadata) => metadata,
+            _ => panic!("runtime metadata is not v14"),
+        };
+        CallDescriber::new(metadata).unwrap().with_asset_decimals(DAO_ID, 4)
+    }
+
+    fn amount(node: &CallNode, name: &str) -> (String, u8, String) {
+        match node.arg(name) {
+            Some(ArgValue::Amount { raw, decimals, display }) => {
+                (raw.clone(), *decimals, display.clone())
+            }
+            other => panic!("{} is not an amount: {:?}", name, other),
+        }
+    }
+
+    fn apply_funds(amount: u128) -> RuntimeCall {
+        RuntimeCall::WeteeProject(WeteeProjectCall::apply_project_funds {
+            dao_id: DAO_ID,
+            project_id: 1,
+            amount,
+        })
+    }
+
+    #[test]
+    fn project_amount_uses_dao_decimals() {
+        let node = describer().describe(&apply_funds(15000)).unwrap();
+        assert_eq!((node.pallet.as_str(), node.call.as_str()), ("WeteeProject", "apply_project_funds"));
+        assert_eq!(node.number_arg("project_id"), Some(1));
+        assert_eq!(amount(&node, "amount"), ("15000".to_owned(), 4, "1.5".to_owned()));
+    }
+
+    #[test]
+    fn unknown_dao_amount_is_raw() {
+        let call = RuntimeCall::WeteeAsset(WeteeAssetsCall::transfer {
+            dest: MultiAddress::Id(AccountId::new([2; 32])),
+            dao_id: 5001,
+            amount: 1_000_000_000_000,
+        });
+        let node = describer().describe(&call).unwrap();
+        assert_eq!(node.arg("amount"), Some(&ArgValue::Number("1000000000000".to_owned())));
+    }
+
+    #[test]
+    fn pledge_uses_dao_decimals() {
+        let call = RuntimeCall::WeteeGov(WeteeGovCall::vote_for_referendum {
+            dao_id: DAO_ID,
+            referendum_index: 0,
+            pledge: Pledge::FungToken(25000),
+            opinion: Opinion::YES,
+        });
+        let node = describer().describe(&call).unwrap();
+        match node.arg("pledge") {
+            Some(ArgValue::Variant { name, fields }) => {
+                assert_eq!(name, "FungToken");
+                assert_eq!(
+                    fields[0].value,
+                    ArgValue::Amount {
+                        raw: "25000".to_owned(),
+                        decimals: 4,
+                        display: "2.5".to_owned(),
+                    }
+                );
+            }
+            other => panic!("pledge is not decoded: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn existential_deposit_is_native() {
+        let call = RuntimeCall::WeteeAsset(WeteeAssetsCall::set_existenial_deposit {
+            dao_id: DAO_ID,
+            existenial_deposit: 1_000_000_000_000,
+        });
+        let node = describer().describe(&call).unwrap();
+        assert_eq!(amount(&node, "existenial_deposit").1, DECIMALS);
+    }
+
+    #[test]
+    fn nested_sudo_call() {
+        let call = RuntimeCall::WeteeSudo(WeteeSudoCall::sudo {
+            dao_id: DAO_ID,
+            call: Box::new(apply_funds(20000)),
+        });
+        let node = describer().describe(&call).unwrap();
+        assert_eq!(node.call, "sudo");
+        match node.arg("call") {
+            Some(ArgValue::Call(inner)) => {
+                assert_eq!(inner.pallet, "WeteeProject");
+                assert_eq!(amount(inner, "amount").2, "2");
+            }
+            other => panic!("call is not decoded: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn balances_call_and_event() {
+        let describer = describer();
+        let call = RuntimeCall::Balances(pallet_balances::Call::transfer {
+            dest: MultiAddress::Id(AccountId::new([2; 32])),
+            value: 2_500_000_000_000,
+        });
+        let node = describer.describe(&call).unwrap();
+        assert_eq!(amount(&node, "value").2, "2.5");
+
+        let event = RuntimeEvent::Balances(pallet_balances::Event::Transfer {
+            from: AccountId::new([1; 32]),
+            to: AccountId::new([2; 32]),
+            amount: 7,
+        });
+        let node = describer.describe_event(&event.encode()).unwrap();
+        assert_eq!((node.pallet.as_str(), node.call.as_str()), ("Balances", "Transfer"));
+        assert_eq!(
+            node.arg("to"),
+            Some(&ArgValue::Address(AccountId::new([2; 32]).to_ss58check()))
+        );
+        assert_eq!(amount(&node, "amount").0, "7");
+    }
+
+    #[test]
+    fn trailing_bytes_rejected() {
+        let mut data = apply_funds(1).encode();
+        data.push(0);
+        assert!(describer().describe_encoded(&data).is_err());
+    }
+}
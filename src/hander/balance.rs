@@ -1,8 +1,9 @@
-use crate::{account::add_pair, model::{account::AssetAccountData, chain::QueryKey}};
+use crate::{account::add_pair, chain::DECIMALS, model::{account::AssetAccountData, amount::Amount, chain::{BlockRef, QueryKey, StorageChange, StorageQuery}}};
 
 use super::super::client::Client;
 
 use codec::{Encode, Decode, MaxEncodedLen};
+use futures::{Stream, StreamExt};
 // use pallet_balances::AccountData;
 use sp_core::{crypto::Ss58Codec, sr25519, Pair, RuntimeDebug};
 use sp_runtime::MultiAddress;
@@ -24,12 +25,13 @@ impl Balance {
     pub async fn balance(
         & self,
         address: String,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<AssetAccountData<u128>, anyhow::Error> {
         let id = sr25519::Public::from_string(&address).unwrap().into();
         let account:AccountInfo<
            Nonce,
            AccountData<u128>,
-        > = self.base.get_storage_map("System", "Account", QueryKey::AccountId(id)).await.unwrap().unwrap_or_default();
+        > = self.base.get_storage_map("System", "Account", QueryKey::AccountId(id), at).await.unwrap().unwrap_or_default();
         Ok(AssetAccountData {
             free: account.data.free,
             frozen: account.data.frozen,
@@ -38,13 +40,44 @@ impl Balance {
         })
     }
 
+    /// 订阅资产变化
+    pub async fn watch_balance(
+        & self,
+        address: String,
+    ) -> anyhow::Result<impl Stream<Item = anyhow::Result<StorageChange<AssetAccountData<u128>>>>, anyhow::Error> {
+        let id = sr25519::Public::from_string(&address).unwrap().into();
+        let stream = self
+            .base
+            .watch_storage::<AccountInfo<Nonce, AccountData<u128>>>(StorageQuery::Map {
+                storage_prefix: "System",
+                storage_key_name: "Account",
+                key: QueryKey::AccountId(id),
+            })
+            .await?;
+
+        Ok(stream.map(|change| {
+            change.map(|c| {
+                let account = c.value.unwrap_or_default();
+                StorageChange {
+                    block: c.block,
+                    value: Some(AssetAccountData {
+                        free: account.data.free,
+                        frozen: account.data.frozen,
+                        reserved: account.data.reserved,
+                    }),
+                }
+            })
+        }))
+    }
+
     /// 转账
     pub async fn transfer(
         & self,
         from: String,
         to: String,
-        amount: u128,
+        amount: Amount,
     ) -> anyhow::Result<(), anyhow::Error> {
+        let amount = amount.ensure_decimals(DECIMALS)?;
         // 构造请求
         let dest = sr25519::Public::from_string(&to).unwrap();
         let call = RuntimeCall::Balances(pallet_balances::Call::transfer { dest: MultiAddress::Id(dest.into()), value: amount });
@@ -55,8 +88,9 @@ impl Balance {
     pub async fn init_from_pair(
         & self,
         to: String,
-        amount: u128,
+        amount: Amount,
     ) -> anyhow::Result<(), anyhow::Error> {
+        let amount = amount.ensure_decimals(DECIMALS)?;
         let alice: sr25519::Pair = Pair::from_string(
             "0xe5be9a5092b81bca64be81d212e7f2f9eba183bb7a90954f7b76361f6edb5c0a",
             None,
@@ -1,4 +1,6 @@
 pub mod balance;
+pub mod reputation;
+pub mod task_workflow;
 pub mod wetee_app;
 pub mod wetee_asset;
 pub mod wetee_org;
@@ -0,0 +1,265 @@
+use sp_core::{crypto::Ss58Codec, sr25519};
+use wetee_project::TaskStatus;
+use wetee_runtime::AccountId;
+
+use super::wetee_project::tasks_of_daos;
+use crate::batch::StorageBatch;
+use crate::client::storage_key_suffix;
+use crate::indexer::history::{HistoryAction, HistoryBackend, HistoryEntry, HistoryFilter, HistoryKind};
+use crate::indexer::store::IndexStore;
+use crate::model::chain::{BlockRef, Page, QueryKey};
+use crate::model::reputation::{
+    CompletedTask, ContributorProfile, DaoPoints, GivenReview, LeaderboardEntry, PointChange,
+};
+use crate::Client;
+
+/// 贡献者声誉
+pub struct Reputation {
+    pub base: Client,
+}
+
+impl Reputation {
+    pub fn new(c: Client) -> Self {
+        Self { base: c }
+    }
+
+    /// 贡献者档案，store 不为 None 时补充完成时间、评审记录和声誉变化原因
+    /// 完成时间和声誉变化来自索引中的任务完成事件，与完成任务的交易由谁签名无关
+    pub async fn profile(
+        &self,
+        address: String,
+        store: Option<&IndexStore>,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<ContributorProfile, anyhow::Error> {
+        let who: AccountId = sr25519::Public::from_string(&address)
+            .map_err(|e| anyhow::anyhow!("invalid address {}: {:?}", address, e))?
+            .into();
+        let dao_ids = self.dao_ids_of(&who, at).await?;
+
+        let mut batch = StorageBatch::new();
+        let points: Vec<_> = dao_ids
+            .iter()
+            .map(|dao_id| {
+                (
+                    *dao_id,
+                    batch.double_map::<u32>(
+                        "WeteeOrg",
+                        "MemberPoint",
+                        QueryKey::U64Key(*dao_id),
+                        QueryKey::AccountId(who.clone()),
+                    ),
+                )
+            })
+            .collect();
+        let result = batch.fetch(&self.base, at).await?;
+        let mut dao_points = vec![];
+        for (dao_id, item) in points.iter() {
+            dao_points.push(DaoPoints {
+                dao_id: *dao_id,
+                point: result.get_or_default(item)?,
+            });
+        }
+
+        let mut guilds = vec![];
+        let mut projects = vec![];
+        for dao_id in dao_ids.iter() {
+            for (scope, list) in [("GuildMembers", &mut guilds), ("ProjectMembers", &mut projects)] {
+                let members: Vec<(String, Vec<AccountId>)> = self
+                    .base
+                    .get_storage_double_map_first("WeteeOrg", scope, QueryKey::U64Key(*dao_id), at)
+                    .await?;
+                for (key, accounts) in members.iter() {
+                    if accounts.contains(&who) {
+                        list.push((*dao_id, storage_key_suffix::<u64>(key, 8)?));
+                    }
+                }
+            }
+        }
+
+        let entries = match store {
+            Some(store) => task_history(store, &who).await?,
+            None => vec![],
+        };
+
+        // 任务完成事件，没有索引时为空
+        let mut done_events = vec![];
+        if let Some(store) = store {
+            for dao_id in dao_ids.iter() {
+                done_events.extend(store.task_done_events(*dao_id).await?);
+            }
+        }
+
+        let mut tasks_done = vec![];
+        for hit in tasks_of_daos(&self.base, &dao_ids, at).await?.into_iter() {
+            if !matches!(hit.task.status, TaskStatus::Done) || !hit.task.assignees.contains(&who) {
+                continue;
+            }
+            let block_number = done_events
+                .iter()
+                .filter(|e| e.project_id == hit.project_id && e.task_id == hit.task.id)
+                .map(|e| e.block_number)
+                .max();
+            tasks_done.push(CompletedTask {
+                dao_id: hit.dao_id,
+                project_id: hit.project_id,
+                task_id: hit.task.id,
+                name: hit.task.name.clone(),
+                point: hit.task.point,
+                block_number,
+            });
+        }
+
+        let reviews = entries
+            .iter()
+            .filter_map(|e| match &e.action {
+                HistoryAction::Task {
+                    dao_id,
+                    project_id,
+                    task_id: Some(task_id),
+                    action,
+                } if action == "make_review" => Some(GivenReview {
+                    dao_id: *dao_id,
+                    project_id: *project_id,
+                    task_id: *task_id,
+                    block_number: e.block_number,
+                    success: e.success,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        // 每个任务完成事件一条记录，事件不带负责人和声誉，按任务当前的负责人和声誉匹配
+        let mut point_changes: Vec<PointChange> = done_events
+            .iter()
+            .filter_map(|e| {
+                tasks_done
+                    .iter()
+                    .find(|t| t.project_id == e.project_id && t.task_id == e.task_id)
+                    .map(|t| PointChange {
+                        dao_id: e.dao_id,
+                        block_number: e.block_number,
+                        point: t.point as u32,
+                        reason: format!("task_done project {} task {}", e.project_id, e.task_id),
+                    })
+            })
+            .collect();
+        point_changes.sort_by_key(|c| c.block_number);
+
+        Ok(ContributorProfile {
+            account: who,
+            points: dao_points,
+            tasks_done,
+            reviews,
+            guilds,
+            projects,
+            point_changes,
+        })
+    }
+
+    /// DAO 声誉排行
+    pub async fn leaderboard(
+        &self,
+        dao_id: u64,
+        page: Page,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Vec<LeaderboardEntry>, anyhow::Error> {
+        let members: Vec<AccountId> = self
+            .base
+            .get_storage_map("WeteeOrg", "Members", QueryKey::U64Key(dao_id), at)
+            .await?
+            .unwrap_or_default();
+
+        let mut batch = StorageBatch::new();
+        let items: Vec<_> = members
+            .iter()
+            .map(|m| {
+                batch.double_map::<u32>(
+                    "WeteeOrg",
+                    "MemberPoint",
+                    QueryKey::U64Key(dao_id),
+                    QueryKey::AccountId(m.clone()),
+                )
+            })
+            .collect();
+        let result = batch.fetch(&self.base, at).await?;
+
+        let tasks = tasks_of_daos(&self.base, &[dao_id], at).await?;
+
+        let mut entries = vec![];
+        for (account, item) in members.into_iter().zip(items.iter()) {
+            let tasks_done = tasks
+                .iter()
+                .filter(|t| matches!(t.task.status, TaskStatus::Done))
+                .filter(|t| t.task.assignees.contains(&account))
+                .count() as u32;
+            entries.push(LeaderboardEntry {
+                rank: 0,
+                account,
+                point: result.get_or_default(item)?,
+                tasks_done,
+            });
+        }
+        entries.sort_by(|a, b| b.point.cmp(&a.point).then(b.tasks_done.cmp(&a.tasks_done)));
+
+        // 声誉相同时排名相同
+        for i in 0..entries.len() {
+            entries[i].rank = if i > 0 && entries[i].point == entries[i - 1].point {
+                entries[i - 1].rank
+            } else {
+                i as u32 + 1
+            };
+        }
+
+        Ok(entries
+            .into_iter()
+            .skip(page.offset as usize)
+            .take(page.limit as usize)
+            .collect())
+    }
+
+    // 账户所在的 DAO
+    async fn dao_ids_of(
+        &self,
+        who: &AccountId,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Vec<u64>, anyhow::Error> {
+        let members: Vec<(String, Vec<AccountId>)> = self
+            .base
+            .get_storage_map_all("WeteeOrg", "Members", at)
+            .await?;
+        let mut dao_ids = vec![];
+        for (key, list) in members.iter() {
+            if list.contains(who) {
+                dao_ids.push(storage_key_suffix::<u64>(key, 8)?);
+            }
+        }
+        dao_ids.sort();
+        Ok(dao_ids)
+    }
+}
+
+// 账户的所有任务操作记录
+async fn task_history(
+    history: &dyn HistoryBackend,
+    who: &AccountId,
+) -> anyhow::Result<Vec<HistoryEntry>> {
+    let filter = HistoryFilter {
+        kinds: vec![HistoryKind::Task],
+        ..Default::default()
+    };
+    let mut page = Page {
+        offset: 0,
+        limit: 500,
+    };
+    let mut entries = vec![];
+    loop {
+        let result = history.query(who, &filter, page).await?;
+        let count = result.entries.len() as u64;
+        entries.extend(result.entries);
+        page.offset += count;
+        if count == 0 || page.offset >= result.total {
+            break;
+        }
+    }
+    Ok(entries)
+}
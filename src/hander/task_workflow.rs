@@ -0,0 +1,326 @@
+use sp_core::{crypto::Ss58Codec, sr25519};
+use wetee_project::TaskStatus;
+use wetee_runtime::AccountId;
+
+use super::wetee_project::WeteeProject;
+use crate::model::dao::{Executed, Execution};
+use crate::model::task::{TaskReview, TaskView};
+use crate::Client;
+
+/// 任务操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskAction {
+    // 加入任务
+    Join,
+    // 离开任务
+    Leave,
+    // 成为评审
+    JoinReview,
+    // 退出评审
+    LeaveReview,
+    // 开始任务
+    Start,
+    // 申请评审
+    RequestReview,
+    // 评审
+    Review,
+    // 完成任务
+    Done,
+}
+
+/// 执行操作的角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskRole {
+    Assignee,
+    Reviewer,
+    Creator,
+    // 其他账户
+    Other,
+}
+
+/// 任务状态机，在发送交易前检查操作是否可以执行，避免支付无效交易的手续费
+pub struct TaskWorkflow {
+    pub project: WeteeProject,
+}
+
+impl TaskWorkflow {
+    pub fn new(c: Client) -> Self {
+        Self {
+            project: WeteeProject::new(c),
+        }
+    }
+
+    /// 账户在任务中的角色
+    pub fn roles(task: &TaskView, who: &AccountId) -> Vec<TaskRole> {
+        let mut roles = vec![];
+        if task.assignees.contains(who) {
+            roles.push(TaskRole::Assignee);
+        }
+        if task.reviewers.contains(who) {
+            roles.push(TaskRole::Reviewer);
+        }
+        if &task.creator == who {
+            roles.push(TaskRole::Creator);
+        }
+        if roles.is_empty() {
+            roles.push(TaskRole::Other);
+        }
+        roles
+    }
+
+    /// 检查账户是否可以执行操作，不可以时返回原因
+    /// 规则与 WeteeProject 的调用检查一致：
+    /// 执行人在 ToDo 时加入、离开和开始任务，InProgress 时申请评审；
+    /// 评审在 InReview 时每人评审一次；
+    /// 所有评审意见均为通过且至少有一个评审后，执行人或创建者才能完成任务
+    pub fn check(
+        task: &TaskView,
+        reviews: &[TaskReview],
+        who: &AccountId,
+        action: TaskAction,
+    ) -> anyhow::Result<()> {
+        let is_assignee = task.assignees.contains(who);
+        let is_reviewer = task.reviewers.contains(who);
+        let is_creator = &task.creator == who;
+        let status = &task.status;
+
+        let (allowed, reason) = match action {
+            TaskAction::Join => (
+                matches!(status, TaskStatus::ToDo)
+                    && !is_assignee
+                    && !is_reviewer
+                    && task.assignees.len() < task.max_assignee as usize,
+                "task is not open for new assignees",
+            ),
+            TaskAction::Leave => (
+                matches!(status, TaskStatus::ToDo) && is_assignee,
+                "only an assignee can leave a task that has not started",
+            ),
+            TaskAction::JoinReview => (
+                matches!(status, TaskStatus::ToDo | TaskStatus::InProgress)
+                    && !is_reviewer
+                    && !is_assignee,
+                "task is not open for new reviewers",
+            ),
+            TaskAction::LeaveReview => (
+                matches!(status, TaskStatus::ToDo | TaskStatus::InProgress) && is_reviewer,
+                "only a reviewer can leave review before the task is in review",
+            ),
+            TaskAction::Start => (
+                matches!(status, TaskStatus::ToDo) && is_assignee,
+                "only an assignee can start a task that is to do",
+            ),
+            TaskAction::RequestReview => (
+                matches!(status, TaskStatus::InProgress) && is_assignee,
+                "only an assignee can request review of a task in progress",
+            ),
+            TaskAction::Review => (
+                matches!(status, TaskStatus::InReview)
+                    && is_reviewer
+                    && !reviews.iter().any(|r| &r.who == who),
+                "only a reviewer who has not reviewed yet can review a task in review",
+            ),
+            TaskAction::Done => (
+                matches!(status, TaskStatus::InReview)
+                    && (is_assignee || is_creator)
+                    && !reviews.is_empty()
+                    && reviews.iter().all(|r| r.approved()),
+                "only an assignee or the creator can finish a task in review approved by every review",
+            ),
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "{:?} not allowed on task {} ({:?}): {}",
+                action,
+                task.id,
+                status,
+                reason
+            ))
+        }
+    }
+
+    /// 账户当前可以执行的操作
+    pub fn next_actions(task: &TaskView, reviews: &[TaskReview], who: &AccountId) -> Vec<TaskAction> {
+        [
+            TaskAction::Join,
+            TaskAction::Leave,
+            TaskAction::JoinReview,
+            TaskAction::LeaveReview,
+            TaskAction::Start,
+            TaskAction::RequestReview,
+            TaskAction::Review,
+            TaskAction::Done,
+        ]
+        .into_iter()
+        .filter(|action| Self::check(task, reviews, who, *action).is_ok())
+        .collect()
+    }
+
+    /// 读取最新的任务信息，检查通过后由签名账户直接执行操作
+    /// 评审需要意见，请使用 review
+    pub async fn perform(
+        &self,
+        from: String,
+        dao_id: u64,
+        project_id: u64,
+        task_id: u64,
+        action: TaskAction,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
+        if action == TaskAction::Review {
+            return Err(anyhow::anyhow!("use review to submit a review opinion"));
+        }
+        self.ensure(&from, project_id, task_id, action).await?;
+
+        // 检查的是签名账户本身，只能直接执行
+        let exec = Execution::Direct;
+        let p = &self.project;
+        match action {
+            TaskAction::Join => p.join_task(from, dao_id, project_id, task_id, exec).await,
+            TaskAction::Leave => p.leave_task(from, dao_id, project_id, task_id, exec).await,
+            TaskAction::JoinReview => p.join_task_review(from, dao_id, project_id, task_id, exec).await,
+            TaskAction::LeaveReview => p.leave_task_review(from, dao_id, project_id, task_id, exec).await,
+            TaskAction::Start => p.start_task(from, dao_id, project_id, task_id, exec).await,
+            TaskAction::RequestReview => p.request_review(from, dao_id, project_id, task_id, exec).await,
+            TaskAction::Done => p.task_done(from, dao_id, project_id, task_id, exec).await,
+            TaskAction::Review => Err(anyhow::anyhow!("use review to submit a review opinion")),
+        }
+    }
+
+    /// 检查通过后由签名账户直接评审任务
+    pub async fn review(
+        &self,
+        from: String,
+        dao_id: u64,
+        project_id: u64,
+        task_id: u64,
+        approve: bool,
+        meta: String,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
+        self.ensure(&from, project_id, task_id, TaskAction::Review).await?;
+        self.project
+            .make_review(from, dao_id, project_id, task_id, approve, meta, Execution::Direct)
+            .await
+    }
+
+    // 读取任务和评审记录并检查操作
+    async fn ensure(
+        &self,
+        from: &str,
+        project_id: u64,
+        task_id: u64,
+        action: TaskAction,
+    ) -> anyhow::Result<()> {
+        let who: AccountId = sr25519::Public::from_string(from)
+            .map_err(|e| anyhow::anyhow!("invalid address {}: {:?}", from, e))?
+            .into();
+        let task = self.project.task_info(project_id, task_id, None).await?;
+        let reviews = self.project.task_reviews(task_id, None).await?;
+        Self::check(&TaskView::from(&task), &reviews, &who, action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wetee_project::ReviewOpinion;
+
+    use super::*;
+
+    fn account(n: u8) -> AccountId {
+        AccountId::new([n; 32])
+    }
+
+    // 创建者 1，执行人 2，评审 3
+    fn task(status: TaskStatus) -> TaskView {
+        TaskView {
+            id: 7,
+            creator: account(1),
+            status,
+            priority: 0,
+            point: 10,
+            amount: 100,
+            max_assignee: 2,
+            assignees: vec![account(2)],
+            reviewers: vec![account(3)],
+            skills: vec![],
+        }
+    }
+
+    fn review(who: u8, approve: bool) -> TaskReview {
+        TaskReview {
+            who: account(who),
+            meta: vec![],
+            option: if approve {
+                ReviewOpinion::YES
+            } else {
+                ReviewOpinion::NO
+            },
+        }
+    }
+
+    fn allowed(task: &TaskView, reviews: &[TaskReview], who: u8, action: TaskAction) -> bool {
+        TaskWorkflow::check(task, reviews, &account(who), action).is_ok()
+    }
+
+    #[test]
+    fn todo_actions() {
+        let todo = task(TaskStatus::ToDo);
+        assert_eq!(
+            TaskWorkflow::next_actions(&todo, &[], &account(2)),
+            vec![TaskAction::Leave, TaskAction::Start]
+        );
+        assert_eq!(
+            TaskWorkflow::next_actions(&todo, &[], &account(4)),
+            vec![TaskAction::Join, TaskAction::JoinReview]
+        );
+        assert!(!allowed(&todo, &[], 1, TaskAction::Start));
+    }
+
+    #[test]
+    fn join_respects_max_assignee() {
+        let mut full = task(TaskStatus::ToDo);
+        full.assignees.push(account(5));
+        assert!(!allowed(&full, &[], 4, TaskAction::Join));
+
+        // 超过 255 个执行人时不能因截断而允许加入
+        let mut many = task(TaskStatus::ToDo);
+        many.max_assignee = 255;
+        many.assignees = (0..=255u8).map(|n| AccountId::new([n; 32])).collect();
+        assert!(!allowed(&many, &[], 100, TaskAction::Join));
+    }
+
+    #[test]
+    fn review_once_in_review() {
+        let in_review = task(TaskStatus::InReview);
+        assert!(allowed(&in_review, &[], 3, TaskAction::Review));
+        assert!(!allowed(&in_review, &[review(3, true)], 3, TaskAction::Review));
+        assert!(!allowed(&in_review, &[], 2, TaskAction::Review));
+        assert!(!allowed(&task(TaskStatus::InProgress), &[], 3, TaskAction::Review));
+    }
+
+    #[test]
+    fn done_requires_approval() {
+        let in_review = task(TaskStatus::InReview);
+        assert!(!allowed(&in_review, &[], 2, TaskAction::Done));
+        assert!(!allowed(&in_review, &[review(3, false)], 2, TaskAction::Done));
+        assert!(allowed(&in_review, &[review(3, true)], 2, TaskAction::Done));
+        assert!(allowed(&in_review, &[review(3, true)], 1, TaskAction::Done));
+        assert!(!allowed(&in_review, &[review(3, true)], 3, TaskAction::Done));
+        assert!(!allowed(&task(TaskStatus::Done), &[review(3, true)], 2, TaskAction::Done));
+    }
+
+    #[test]
+    fn in_progress_flow() {
+        let in_progress = task(TaskStatus::InProgress);
+        assert_eq!(
+            TaskWorkflow::next_actions(&in_progress, &[], &account(2)),
+            vec![TaskAction::RequestReview]
+        );
+        assert_eq!(
+            TaskWorkflow::next_actions(&in_progress, &[], &account(3)),
+            vec![TaskAction::LeaveReview]
+        );
+    }
+}
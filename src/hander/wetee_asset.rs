@@ -1,9 +1,13 @@
-use super::super::client::Client;
-use crate::model::{account::AssetAccountData, chain::QueryKey};
+use super::{super::client::Client, wetee_gov::execute};
+use crate::batch::StorageBatch;
+use crate::chain::DECIMALS;
+use crate::model::{account::AssetAccountData, amount::Amount, asset::{AssetHolder, AssetInfo, HolderPage}, chain::{BlockRef, Page, QueryKey}, dao::{Executed, Execution}};
 
 use sp_core::{crypto::Ss58Codec, sr25519};
-use sp_runtime::MultiAddress;
-use wetee_runtime::{RuntimeCall, WeteeAssetsCall};
+use sp_runtime::{MultiAddress, Permill};
+use wetee_assets::{DaoAssetInfo, DaoAssetMeta};
+use wetee_org::OrgInfo;
+use wetee_runtime::{AccountId, Balance, BlockNumber, RuntimeCall, WeteeAssetsCall};
 
 /// 账户
 pub struct WeteeAsset {
@@ -20,6 +24,7 @@ impl WeteeAsset {
         & self,
         dao_id: u64,
         address: String,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<AssetAccountData<u128>, anyhow::Error> {
         let id = sr25519::Public::from_string(&address).unwrap().into();
         let balance: AssetAccountData<u128> = self
@@ -29,6 +34,7 @@ impl WeteeAsset {
                 "Accounts",
                 QueryKey::AccountId(id),
                 QueryKey::U64Key(dao_id),
+                at,
             )
             .await
             .unwrap()
@@ -37,42 +43,128 @@ impl WeteeAsset {
         Ok(balance)
     }
 
-    /// 创建资产
+    /// 按 DAO 代币精度解析数量，例如 "1.5"
+    pub async fn parse_amount(& self, dao_id: u64, value: &str) -> anyhow::Result<Amount, anyhow::Error> {
+        Amount::parse(value, asset_decimals(&self.base, dao_id).await?)
+    }
+
+    /// 资产元数据、发行总量和最低存款，资产未创建时返回 None
+    pub async fn asset_info(
+        & self,
+        dao_id: u64,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Option<AssetInfo>, anyhow::Error> {
+        let mut batch = StorageBatch::new();
+        let info = batch.map::<DaoAssetInfo<AccountId, DaoAssetMeta>>(
+            "WeteeAsset",
+            "DaoAssetsInfo",
+            QueryKey::U64Key(dao_id),
+        );
+        let total_issuance = batch.map::<Balance>("Tokens", "TotalIssuance", QueryKey::U64Key(dao_id));
+        let existential_deposit =
+            batch.map::<Balance>("WeteeAsset", "ExistenialDeposits", QueryKey::U64Key(dao_id));
+        let result = batch.fetch(&self.base, at).await?;
+
+        let metadata = match result.get(&info)? {
+            Some(info) => info.metadata,
+            None => return Ok(None),
+        };
+        Ok(Some(AssetInfo {
+            dao_id,
+            name: String::from_utf8_lossy(&metadata.name).to_string(),
+            symbol: String::from_utf8_lossy(&metadata.symbol).to_string(),
+            decimals: metadata.decimals,
+            metadata,
+            total_issuance: result.get_or_default(&total_issuance)?,
+            existential_deposit: result.get_or_default(&existential_deposit)?,
+        }))
+    }
+
+    /// 资产持有人，按持有量倒序分页
+    /// 链上没有按资产索引的账户，候选持有人为 DAO 成员和 DAO 账户，余额在一次批量请求中读取
+    /// 代币转给非成员账户后，这些账户不在列表中
+    pub async fn holders(
+        & self,
+        dao_id: u64,
+        page: Page,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<HolderPage, anyhow::Error> {
+        let mut batch = StorageBatch::new();
+        let info = batch.map::<OrgInfo<AccountId, BlockNumber>>("WeteeOrg", "Daos", QueryKey::U64Key(dao_id));
+        let members = batch.map::<Vec<AccountId>>("WeteeOrg", "Members", QueryKey::U64Key(dao_id));
+        let total_issuance = batch.map::<Balance>("Tokens", "TotalIssuance", QueryKey::U64Key(dao_id));
+        let result = batch.fetch(&self.base, at).await?;
+
+        let info = result
+            .get(&info)?
+            .ok_or_else(|| anyhow::anyhow!("dao {} not found", dao_id))?;
+        let mut candidates = result.get_or_default(&members)?;
+        if !candidates.contains(&info.dao_account_id) {
+            candidates.push(info.dao_account_id);
+        }
+
+        let mut batch = StorageBatch::new();
+        let items: Vec<_> = candidates
+            .iter()
+            .map(|account| {
+                batch.double_map::<AssetAccountData<Balance>>(
+                    "Tokens",
+                    "Accounts",
+                    QueryKey::AccountId(account.clone()),
+                    QueryKey::U64Key(dao_id),
+                )
+            })
+            .collect();
+        let balances = batch.fetch(&self.base, at).await?;
+
+        let mut accounts = vec![];
+        for (account, item) in candidates.into_iter().zip(items.iter()) {
+            accounts.push((account, balances.get_or_default(item)?));
+        }
+
+        Ok(rank_holders(accounts, result.get_or_default(&total_issuance)?, page))
+    }
+
+    /// 创建资产，amount 和 init_dao_asset 的精度需要与 decimals 一致
     pub async fn create_asset(
         & self,
         from: String,
         dao_id: u64,
         meta_name: String,
         meta_symbol: String,
-        amount: u128,
-        init_dao_asset: u128,
-    ) -> anyhow::Result<(), anyhow::Error> {
-        // 构建请求
+        decimals: u8,
+        amount: Amount,
+        init_dao_asset: Amount,
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         let call = RuntimeCall::WeteeAsset(WeteeAssetsCall::create_asset {
             dao_id,
             metadata: wetee_assets::DaoAssetMeta {
                 name: meta_name.into(),
                 symbol: meta_symbol.into(),
-                decimals: 4,
+                decimals,
             },
-            amount,
-            init_dao_asset,
+            amount: amount.ensure_decimals(decimals)?,
+            init_dao_asset: init_dao_asset.ensure_decimals(decimals)?,
         });
-        self.base.send_and_sign(call, from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
-    /// 设置资产
+    /// 设置加入 DAO 需要的押金，押金为原生代币
     pub async fn set_existenial_deposit(
         & self,
         from: String,
         dao_id: u64,
-        amount: u128,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        amount: Amount,
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
+        // 与 join_request 一致，押金为原生代币
+        let amount = amount.ensure_decimals(DECIMALS)?;
         let call = RuntimeCall::WeteeAsset(WeteeAssetsCall::set_existenial_deposit {
             dao_id,
             existenial_deposit: amount,
         });
-        self.base.send_and_sign(call, from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     /// 设置资产元数据
@@ -81,9 +173,10 @@ impl WeteeAsset {
         from: String,
         dao_id: u64,
         metadata: wetee_assets::DaoAssetMeta,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         let call = RuntimeCall::WeteeAsset(WeteeAssetsCall::set_metadata { dao_id, metadata });
-        self.base.send_and_sign(call, from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     /// 销毁资产
@@ -91,10 +184,12 @@ impl WeteeAsset {
         & self,
         from: String,
         dao_id: u64,
-        amount: u128,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        amount: Amount,
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
+        let amount = amount.ensure_decimals(asset_decimals(&self.base, dao_id).await?)?;
         let call = RuntimeCall::WeteeAsset(WeteeAssetsCall::burn { dao_id, amount });
-        self.base.send_and_sign(call, from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     /// 转移资产
@@ -103,32 +198,109 @@ impl WeteeAsset {
         from: String,
         dao_id: u64,
         to: String,
-        amount: u128,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        amount: Amount,
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
-        let dest = sr25519::Public::from_string(&to).unwrap();
+        let amount = amount.ensure_decimals(asset_decimals(&self.base, dao_id).await?)?;
+        let dest = sr25519::Public::from_string(&to)
+            .map_err(|e| anyhow::anyhow!("invalid address {}: {:?}", to, e))?;
         let call = RuntimeCall::WeteeAsset(WeteeAssetsCall::transfer {
             dao_id,
             amount,
             dest: MultiAddress::Id(dest.into()),
         });
-        self.base.send_and_sign(call, from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
-    /// 加入 DAO
+    /// 加入 DAO，加入的账户为签名账户，只能直接签名执行
     pub async fn join_request(
         & self,
         from: String,
         dao_id: u64,
         share_expect: u32,
-        existenial_deposit: u128,
+        existenial_deposit: Amount,
     ) -> anyhow::Result<(), anyhow::Error> {
-        // 构建请求
+        // 构建请求，押金为原生代币
         let call = RuntimeCall::WeteeAsset(WeteeAssetsCall::join_request {
             dao_id,
             share_expect,
-            existenial_deposit,
+            existenial_deposit: existenial_deposit.ensure_decimals(DECIMALS)?,
         });
         self.base.send_and_sign(call, from).await
     }
 }
+
+// 去掉余额为 0 的账户，按持有量倒序排列后分页
+fn rank_holders(
+    accounts: Vec<(AccountId, AssetAccountData<Balance>)>,
+    total_issuance: Balance,
+    page: Page,
+) -> HolderPage {
+    let mut holders = vec![];
+    for (account, balance) in accounts.into_iter() {
+        let total = balance.free.saturating_add(balance.reserved);
+        if total == 0 {
+            continue;
+        }
+        holders.push(AssetHolder {
+            account,
+            balance,
+            total,
+            share: if total_issuance == 0 {
+                Permill::zero()
+            } else {
+                Permill::from_rational(total, total_issuance)
+            },
+        });
+    }
+    holders.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.account.cmp(&b.account)));
+
+    HolderPage {
+        total: holders.len() as u64,
+        total_issuance,
+        holders: holders
+            .into_iter()
+            .skip(page.offset as usize)
+            .take(page.limit as usize)
+            .collect(),
+    }
+}
+
+/// DAO 代币精度，资产未创建时报错
+pub(crate) async fn asset_decimals(client: &Client, dao_id: u64) -> anyhow::Result<u8> {
+    let info: Option<DaoAssetInfo<AccountId, DaoAssetMeta>> = client
+        .get_storage_map("WeteeAsset", "DaoAssetsInfo", QueryKey::U64Key(dao_id), None)
+        .await?;
+    info.map(|info| info.metadata.decimals)
+        .ok_or_else(|| anyhow::anyhow!("dao {} has no asset", dao_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holder(n: u8, free: Balance) -> (AccountId, AssetAccountData<Balance>) {
+        (
+            AccountId::new([n; 32]),
+            AssetAccountData {
+                free,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn rank_holders_sorts_filters_and_pages() {
+        let accounts = vec![holder(1, 10), holder(2, 0), holder(3, 30), holder(4, 20)];
+        let page = rank_holders(accounts, 100, Page { offset: 1, limit: 1 });
+        assert_eq!(page.total, 3);
+        assert_eq!(page.total_issuance, 100);
+        assert_eq!(page.holders.len(), 1);
+        assert_eq!(page.holders[0].account, AccountId::new([4; 32]));
+        assert_eq!(page.holders[0].share, Permill::from_percent(20));
+
+        let page = rank_holders(vec![holder(1, 10)], 0, Page { offset: 0, limit: 10 });
+        assert_eq!(page.holders[0].share, Permill::zero());
+    }
+}
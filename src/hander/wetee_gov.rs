@@ -1,32 +1,72 @@
 
-use crate::{model::{dao::WithGov, chain::QueryKey}, Client};
+use crate::{batch::StorageBatch, chain::DECIMALS, client::storage_key_suffix, model::{amount::Amount, dao::{Executed, Execution}, chain::{BlockRef, QueryKey}, gov::{call_name, percent, pledge_amount, GovPeriod, LockedVote, Proposal, ReferendumView, Unlockable, VotePreview}}, Client};
 use sp_core::{crypto::Ss58Codec, sr25519};
-pub use wetee_gov::{MemmberData, Opinion, Referendum, ReferendumStatus};
+pub use wetee_gov::{MemmberData, Opinion, Period, Referendum, ReferendumStatus};
 use wetee_gov::{ReferendumIndex, VoteInfo};
 pub use wetee_runtime::Pledge;
 use wetee_runtime::{
     AccountId, Balance, BlockNumber, Hash, RuntimeCall, WeteeGovCall, WeteeSudoCall,
 };
 
-// 通过 sudo 或者 gov 执行区块链函数
-pub async fn run_sudo_or_gov(
+// 按执行方式执行修改 DAO 状态的调用
+pub async fn execute(
     client: &Client,
     from: String,
     dao_id: u64,
     call: RuntimeCall,
-    param: WithGov,
-) -> anyhow::Result<(), anyhow::Error> {
-    let _result = if param.run_type == 1 {
-        let call = RuntimeCall::WeteeGov(WeteeGovCall::create_propose { 
-            dao_id: dao_id, 
-            member_data: param.member, 
-            proposal:Box::new(call), 
-            period_index: param.period_index,
-        });
-        return client.send_and_sign(call,from).await;
+    exec: Execution,
+) -> anyhow::Result<Executed, anyhow::Error> {
+    let (period_index, member_data, deposit) = match exec {
+        Execution::Direct => {
+            let block_hash = client.send_and_sign_in_block(call, from).await?;
+            return Ok(Executed::Immediately { block_hash });
+        }
+        Execution::Sudo => {
+            let call = RuntimeCall::WeteeSudo(WeteeSudoCall::sudo { dao_id, call: Box::new(call) });
+            let block_hash = client.send_and_sign_in_block(call, from).await?;
+            return Ok(Executed::Immediately { block_hash });
+        }
+        Execution::Gov { period, member, deposit } => (period, member, deposit),
     };
-    let call = RuntimeCall::WeteeSudo(WeteeSudoCall::sudo { dao_id: dao_id, call: Box::new(call)});
-    client.send_and_sign(call,from).await
+
+    let proposer: AccountId = sr25519::Public::from_string(&from)
+        .map_err(|e| anyhow::anyhow!("invalid address {}: {:?}", from, e))?
+        .into();
+    let propose = RuntimeCall::WeteeGov(WeteeGovCall::create_propose {
+        dao_id,
+        member_data,
+        proposal: Box::new(call.clone()),
+        period_index,
+    });
+    let block_hash = client.send_and_sign_in_block(propose, from.clone()).await?;
+
+    // 在交易所在区块中查找刚创建的提案
+    let props: Vec<(u32, Hash, RuntimeCall, MemmberData, AccountId)> = client
+        .get_storage_map("WeteeGov", "PublicProps", QueryKey::U64Key(dao_id), Some(BlockRef::Hash(block_hash)))
+        .await?
+        .unwrap_or_default();
+    let index = props
+        .into_iter()
+        .filter(|(_, _, c, _, who)| who == &proposer && c == &call)
+        .map(|(index, ..)| index)
+        .max()
+        .ok_or_else(|| anyhow::anyhow!("proposal not found in block {:?}", block_hash))?;
+
+    let mut started = false;
+    if deposit > 0 {
+        let start = RuntimeCall::WeteeGov(WeteeGovCall::start_referendum {
+            dao_id,
+            propose_id: index,
+            deposit,
+        });
+        // 提案已创建，错误中带上提案序号，可以稍后再开始投票
+        client.send_and_sign_in_block(start, from).await.map_err(|e| {
+            anyhow::anyhow!("proposal {} created but start referendum failed: {:?}", index, e)
+        })?;
+        started = true;
+    }
+
+    Ok(Executed::Proposed { index, started, block_hash })
 }
 
 /// DAO 治理模块
@@ -43,25 +83,233 @@ impl WeteeGov {
     pub async fn pending_referendum_list(
         & self,
         dao_id: u64,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Vec<(u32, Hash, RuntimeCall, MemmberData, AccountId)>, anyhow::Error> {
-        let result: Vec<(u32, Hash, RuntimeCall, MemmberData, AccountId)> = self.base.get_storage_map("WeteeGov", "PublicProps", QueryKey::U64Key(dao_id)).await
+        let result: Vec<(u32, Hash, RuntimeCall, MemmberData, AccountId)> = self.base.get_storage_map("WeteeGov", "PublicProps", QueryKey::U64Key(dao_id), at).await
             .unwrap()
             .unwrap_or_else(|| vec![]);
         Ok(result)
     }
 
+    // DAO 的治理周期配置
+    pub async fn periods(
+        & self,
+        dao_id: u64,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Vec<GovPeriod>, anyhow::Error> {
+        let periods: Vec<Period<Balance, BlockNumber>> = self
+            .base
+            .get_storage_map("WeteeGov", "Periods", QueryKey::U64Key(dao_id), at)
+            .await?
+            .unwrap_or_default();
+
+        Ok(periods
+            .into_iter()
+            .enumerate()
+            .map(|(index, period)| GovPeriod::from_period(index as u32, period))
+            .collect())
+    }
+
+    // 适用于调用的治理周期
+    pub async fn period_for_call(
+        & self,
+        dao_id: u64,
+        call: &RuntimeCall,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<GovPeriod, anyhow::Error> {
+        self.periods(dao_id, at)
+            .await?
+            .into_iter()
+            .find(|p| p.accepts(call))
+            .ok_or_else(|| {
+                anyhow::anyhow!("dao {} has no gov period for {}", dao_id, call_name(call))
+            })
+    }
+
+    // 账户是否可以在成员范围内发起提案
+    pub async fn can_propose(
+        & self,
+        dao_id: u64,
+        address: String,
+        member: &MemmberData,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<bool, anyhow::Error> {
+        let who: AccountId = sr25519::Public::from_string(&address)
+            .map_err(|e| anyhow::anyhow!("invalid address {}: {:?}", address, e))?
+            .into();
+        let members: Option<Vec<AccountId>> = match member {
+            MemmberData::GLOBAL => {
+                self.base
+                    .get_storage_map("WeteeOrg", "Members", QueryKey::U64Key(dao_id), at)
+                    .await?
+            }
+            MemmberData::GUILD(guild_id) => {
+                self.base
+                    .get_storage_double_map(
+                        "WeteeOrg",
+                        "GuildMembers",
+                        QueryKey::U64Key(dao_id),
+                        QueryKey::U64Key(*guild_id),
+                        at,
+                    )
+                    .await?
+            }
+            MemmberData::PROJECT(project_id) => {
+                self.base
+                    .get_storage_double_map(
+                        "WeteeOrg",
+                        "ProjectMembers",
+                        QueryKey::U64Key(dao_id),
+                        QueryKey::U64Key(*project_id),
+                        at,
+                    )
+                    .await?
+            }
+        };
+        Ok(members.unwrap_or_default().contains(&who))
+    }
+
+    // 为调用选择治理周期，生成通过提案执行的参数，押金为周期的开始投票押金
+    pub async fn with_gov(
+        & self,
+        dao_id: u64,
+        call: &RuntimeCall,
+        member: MemmberData,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Execution, anyhow::Error> {
+        let period = self.period_for_call(dao_id, call, at).await?;
+        Ok(Execution::Gov {
+            period: period.index,
+            member,
+            deposit: period.decision_deposit,
+        })
+    }
+
+    // 待开始的提案
+    pub async fn proposals(
+        & self,
+        dao_id: u64,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Vec<Proposal>, anyhow::Error> {
+        let list = self.pending_referendum_list(dao_id, at).await?;
+        Ok(list
+            .into_iter()
+            .map(|(index, hash, call, member, proposer)| Proposal {
+                dao_id,
+                index,
+                hash,
+                call_name: call_name(&call),
+                call,
+                member,
+                proposer,
+            })
+            .collect())
+    }
+
+    // 所有投票详情
+    pub async fn referendums(
+        & self,
+        dao_id: u64,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Vec<ReferendumView>, anyhow::Error> {
+        let mut items = vec![];
+        for (key, referendum) in self.referendum_list(dao_id, at).await?.into_iter() {
+            items.push((storage_key_suffix::<u32>(&key, 4)?, referendum));
+        }
+        items.sort_by_key(|(index, _)| *index);
+        self.referendum_views(dao_id, items, at).await
+    }
+
+    // 单个投票详情
+    pub async fn referendum(
+        & self,
+        dao_id: u64,
+        index: u32,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Option<ReferendumView>, anyhow::Error> {
+        let referendum: Option<Referendum<BlockNumber, RuntimeCall, Balance>> = self
+            .base
+            .get_storage_double_map(
+                "WeteeGov",
+                "ReferendumInfoOf",
+                QueryKey::U64Key(dao_id),
+                QueryKey::U32Key(index),
+                at,
+            )
+            .await?;
+        match referendum {
+            Some(referendum) => Ok(self
+                .referendum_views(dao_id, vec![(index, referendum)], at)
+                .await?
+                .pop()),
+            None => Ok(None),
+        }
+    }
+
+    // 结合治理周期、发行总量和当前时间计算投票详情
+    async fn referendum_views(
+        & self,
+        dao_id: u64,
+        items: Vec<(u32, Referendum<BlockNumber, RuntimeCall, Balance>)>,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Vec<ReferendumView>, anyhow::Error> {
+        let mut batch = StorageBatch::new();
+        let periods = batch.map::<Vec<Period<Balance, BlockNumber>>>(
+            "WeteeGov",
+            "Periods",
+            QueryKey::U64Key(dao_id),
+        );
+        let total_issuance = batch.map::<u128>("Tokens", "TotalIssuance", QueryKey::U64Key(dao_id));
+        let now = batch.value::<u64>("Timestamp", "Now");
+        // 区块高度和时间从同一个区块读取
+        let number = batch.value::<BlockNumber>("System", "Number");
+        let result = batch.fetch(&self.base, at).await?;
+        let periods = result.get_or_default(&periods)?;
+        let total_issuance = result.get_or_default(&total_issuance)?;
+        let now = result.get_or_default(&now)?;
+        let current: u64 = result.get_or_default(&number)?.into();
+        let block_time = self.base.block_time().await?;
+
+        Ok(items
+            .into_iter()
+            .map(|(index, r)| {
+                let period = periods.get(r.period_index as usize);
+                let end: u64 = r.end.into();
+                let blocks_left = end.saturating_sub(current);
+                ReferendumView {
+                    dao_id,
+                    index,
+                    call_name: call_name(&r.proposal),
+                    call: r.proposal,
+                    member: r.member_data,
+                    period_index: r.period_index,
+                    status: r.status,
+                    yes: r.tally.yes,
+                    no: r.tally.no,
+                    approval: percent(r.tally.yes, r.tally.yes.saturating_add(r.tally.no)),
+                    turnout: percent(r.tally.yes.saturating_add(r.tally.no), total_issuance),
+                    min_approval: period.map(|p| p.min_approval),
+                    min_turnout: period.map(|p| p.min_support),
+                    end: r.end,
+                    blocks_left,
+                    estimated_end: now + blocks_left * block_time,
+                }
+            })
+            .collect())
+    }
+
     // 开始一个投票
     pub async fn start_referendum(
         & self,
         from: String,
         dao_id: u64,
         propose_id: u32,
-        deposit: u128,
+        deposit: Amount,
     ) -> anyhow::Result<(), anyhow::Error> {
         let call = RuntimeCall::WeteeGov(WeteeGovCall::start_referendum {
             dao_id, 
             propose_id,
-            deposit,
+            deposit: deposit.ensure_decimals(DECIMALS)?,
         });
         self.base.send_and_sign(call,from).await
     }
@@ -70,37 +318,188 @@ impl WeteeGov {
     pub async fn referendum_list(
         & self,
         dao_id: u64,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Vec<(String, Referendum<BlockNumber, RuntimeCall, Balance>)>, anyhow::Error>
     {
-        let results: Vec<(String, Referendum<BlockNumber, RuntimeCall, Balance>)> = self.base.get_storage_double_map_first("WeteeGov", "ReferendumInfoOf", QueryKey::U64Key(dao_id)).await
+        let results: Vec<(String, Referendum<BlockNumber, RuntimeCall, Balance>)> = self.base.get_storage_double_map_first("WeteeGov", "ReferendumInfoOf", QueryKey::U64Key(dao_id), at).await
             .unwrap();
 
         Ok(results)
     }
 
-    // 投票
+    // 投票，支持 runtime 中的所有质押方式
     pub async fn vote_for_referendum(
         & self,
         from: String,
         dao_id: u64,
         referendum_index: u32,
-        vote: u64,
-        opinion: bool,
+        pledge: Pledge<Balance>,
+        opinion: Opinion,
     ) -> anyhow::Result<(), anyhow::Error> {
         let call = RuntimeCall::WeteeGov(WeteeGovCall::vote_for_referendum {
             dao_id,
             referendum_index,
-            pledge: Pledge::FungToken(vote.into()),
-            opinion: if opinion { Opinion::YES } else { Opinion::NO },
+            pledge,
+            opinion,
+        });
+        self.base.send_and_sign(call,from).await
+    }
+
+    // 撤销投票
+    pub async fn cancel_vote(
+        & self,
+        from: String,
+        dao_id: u64,
+        referendum_index: u32,
+    ) -> anyhow::Result<(), anyhow::Error> {
+        let call = RuntimeCall::WeteeGov(WeteeGovCall::cancel_vote {
+            dao_id,
+            index: referendum_index,
         });
         self.base.send_and_sign(call,from).await
     }
 
+    // 修改投票，已投票时先撤销原投票
+    // runtime 没有 batch 调用，撤销和投票是两笔交易，不是原子操作
+    // 撤销成功而投票失败时账户在该投票中没有投票，返回的错误会说明
+    pub async fn change_vote(
+        & self,
+        from: String,
+        dao_id: u64,
+        referendum_index: u32,
+        pledge: Pledge<Balance>,
+        opinion: Opinion,
+    ) -> anyhow::Result<(), anyhow::Error> {
+        let voted = self
+            .votes_of_user(from.clone(), dao_id, None)
+            .await?
+            .iter()
+            .any(|v| v.referendum_index == referendum_index);
+        if !voted {
+            return self.vote_for_referendum(from, dao_id, referendum_index, pledge, opinion).await;
+        }
+
+        self.cancel_vote(from.clone(), dao_id, referendum_index).await?;
+        self.vote_for_referendum(from, dao_id, referendum_index, pledge, opinion)
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "previous vote on referendum {} was cancelled but the new vote failed: {}",
+                    referendum_index,
+                    e
+                )
+            })
+    }
+
+    // 投票前预览锁定数量和投票结束区块
+    pub async fn preview_vote(
+        & self,
+        from: String,
+        dao_id: u64,
+        referendum_index: u32,
+        pledge: Pledge<Balance>,
+        opinion: Opinion,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<VotePreview, anyhow::Error> {
+        let referendum = self
+            .referendum(dao_id, referendum_index, at)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("referendum {} of dao {} not found", referendum_index, dao_id))?;
+        if !matches!(referendum.status, ReferendumStatus::Ongoing) {
+            return Err(anyhow::anyhow!("referendum {} is not ongoing", referendum_index));
+        }
+        let existing = self
+            .votes_of_user(from, dao_id, at)
+            .await?
+            .into_iter()
+            .find(|v| v.referendum_index == referendum_index)
+            .map(|v| v.pledge);
+
+        Ok(VotePreview {
+            dao_id,
+            referendum_index,
+            locked: pledge_amount(&pledge),
+            pledge,
+            opinion,
+            existing,
+            referendum_end: referendum.end,
+            estimated_end: referendum.estimated_end,
+        })
+    }
+
+    // 账户在 DAO 中锁定的投票，以及调用 unlock 时可以释放的数量
+    // 投票结束且到达解锁区块后才会释放
+    pub async fn unlockable(
+        & self,
+        dao_id: u64,
+        who: String,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Unlockable, anyhow::Error> {
+        let votes = self.votes_of_user(who, dao_id, at).await?;
+
+        let mut batch = StorageBatch::new();
+        let items: Vec<_> = votes
+            .iter()
+            .map(|v| {
+                batch.double_map::<Referendum<BlockNumber, RuntimeCall, Balance>>(
+                    "WeteeGov",
+                    "ReferendumInfoOf",
+                    QueryKey::U64Key(dao_id),
+                    QueryKey::U32Key(v.referendum_index),
+                )
+            })
+            .collect();
+        let number = batch.value::<BlockNumber>("System", "Number");
+        let result = batch.fetch(&self.base, at).await?;
+        let current = result.get_or_default(&number)?;
+
+        let mut locked_votes = vec![];
+        for (vote, item) in votes.into_iter().zip(items.iter()) {
+            let ongoing = result
+                .get(item)?
+                .map_or(false, |r| matches!(r.status, ReferendumStatus::Ongoing));
+            let reason = if ongoing {
+                Some(format!("referendum {} is still ongoing", vote.referendum_index))
+            } else if vote.unlock_block > current {
+                Some(format!("locked until block {}", vote.unlock_block))
+            } else {
+                None
+            };
+            locked_votes.push(LockedVote {
+                referendum_index: vote.referendum_index,
+                amount: pledge_amount(&vote.pledge),
+                pledge: vote.pledge,
+                unlock_block: vote.unlock_block,
+                ongoing,
+                unlockable: reason.is_none(),
+                reason,
+            });
+        }
+
+        Ok(Unlockable {
+            dao_id,
+            block_number: current,
+            locked: locked_votes.iter().filter_map(|v| v.amount).sum(),
+            unlockable: locked_votes
+                .iter()
+                .filter(|v| v.unlockable)
+                .filter_map(|v| v.amount)
+                .sum(),
+            next_unlock: locked_votes
+                .iter()
+                .filter(|v| !v.unlockable)
+                .map(|v| v.unlock_block.max(current + 1))
+                .min(),
+            votes: locked_votes,
+        })
+    }
+
     // 获取投票结果
     pub async fn votes_of_user(
         & self,
         from: String,
         dao_id: u64,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<
         Vec<VoteInfo<u64, Pledge<Balance>, BlockNumber, Balance, Opinion, ReferendumIndex>>,
         anyhow::Error,
@@ -109,7 +508,7 @@ impl WeteeGov {
 
         let result: Vec<
             VoteInfo<u64, Pledge<Balance>, BlockNumber, Balance, Opinion, ReferendumIndex>,
-        > = self.base.get_storage_map("WeteeGov", "VotesOf", QueryKey::AccountId(dest)).await
+        > = self.base.get_storage_map("WeteeGov", "VotesOf", QueryKey::AccountId(dest), at).await
             .unwrap()
             .unwrap_or_default();
 
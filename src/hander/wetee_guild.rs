@@ -1,7 +1,8 @@
-use crate::model::chain::QueryKey;
-use crate::model::dao::WithGov;
+use crate::batch::StorageBatch;
+use crate::model::chain::{BlockRef, QueryKey};
+use crate::model::dao::{Executed, Execution};
 
-use super::{super::client::Client, wetee_gov::run_sudo_or_gov};
+use super::{super::client::Client, wetee_gov::execute};
 use sp_core::crypto::Ss58Codec;
 use sp_core::sr25519;
 use sp_runtime::AccountId32;
@@ -9,6 +10,8 @@ use wetee_org::GuildInfo;
 use wetee_runtime::{AccountId, BlockNumber, RuntimeCall, WeteeGuildCall};
 
 /// 账户
+/// 链上 WeteeGuild 目前只有 create_guild 和 guild_join_request 两个调用，
+/// 更新工会信息、离开工会、移除成员和关闭工会在运行时中都没有对应调用，这里不提供
 pub struct WeteeGuild {
     pub base: Client,
 }
@@ -22,28 +25,93 @@ impl WeteeGuild {
     pub async fn guild_list(
         & self,
         dao_id: u64,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Vec<GuildInfo<AccountId, BlockNumber>>, anyhow::Error> {
         
         // 构建请求
-        let result: Vec<GuildInfo<AccountId, BlockNumber>> = self.base.get_storage_map("WeteeOrg", "Guilds", QueryKey::U64Key(dao_id)).await
+        let result: Vec<GuildInfo<AccountId, BlockNumber>> = self.base.get_storage_map("WeteeOrg", "Guilds", QueryKey::U64Key(dao_id), at).await
             .unwrap()
             .unwrap_or_else(|| vec![]);
 
         Ok(result)
     }
 
-    /// 工会信息
+    /// 工会信息，index 为工会在 Guilds 列表中的位置
     pub async fn guild_info(
         & self,
         dao_id: u64,
         index: u32,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<GuildInfo<AccountId, BlockNumber>, anyhow::Error> {
-        // 构建请求
-        let result: Vec<GuildInfo<AccountId, BlockNumber>> = self.base.get_storage_map("WeteeOrg", "Guilds", QueryKey::U64Key(dao_id)).await
-            .unwrap()
-            .unwrap_or_else(|| vec![]);
+        let result = self.guild_list(dao_id, at).await?;
+        result
+            .into_iter()
+            .nth(index as usize)
+            .ok_or_else(|| anyhow::anyhow!("guild index {} out of range in dao {}", index, dao_id))
+    }
+
+    /// 按 ID 查找工会，不存在时返回 None
+    /// 链上 Guilds 以 DAO 为单位保存为一个列表，无法按 ID 单独读取，会下载整个列表
+    /// 需要频繁查询时使用索引 IndexStore::guild
+    pub async fn find_guild(
+        & self,
+        dao_id: u64,
+        guild_id: u64,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Option<GuildInfo<AccountId, BlockNumber>>, anyhow::Error> {
+        let result: Vec<GuildInfo<AccountId, BlockNumber>> = self
+            .base
+            .get_storage_map("WeteeOrg", "Guilds", QueryKey::U64Key(dao_id), at)
+            .await?
+            .unwrap_or_default();
+
+        Ok(result.into_iter().find(|g| g.id == guild_id))
+    }
+
+    /// 工会信息和成员，一次请求获取，同样会下载整个 Guilds 列表
+    pub async fn guild_with_members(
+        & self,
+        dao_id: u64,
+        guild_id: u64,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Option<(GuildInfo<AccountId, BlockNumber>, Vec<AccountId>)>, anyhow::Error> {
+        let mut batch = StorageBatch::new();
+        let guilds = batch.map::<Vec<GuildInfo<AccountId, BlockNumber>>>(
+            "WeteeOrg",
+            "Guilds",
+            QueryKey::U64Key(dao_id),
+        );
+        let members = batch.double_map::<Vec<AccountId>>(
+            "WeteeOrg",
+            "GuildMembers",
+            QueryKey::U64Key(dao_id),
+            QueryKey::U64Key(guild_id),
+        );
+        let result = batch.fetch(&self.base, at).await?;
+
+        let guild = result
+            .get_or_default(&guilds)?
+            .into_iter()
+            .find(|g| g.id == guild_id);
+        match guild {
+            Some(guild) => Ok(Some((guild, result.get_or_default(&members)?))),
+            None => Ok(None),
+        }
+    }
 
-        Ok(result.get(index as usize).unwrap().clone())
+    /// 账户是否为工会成员
+    pub async fn is_member(
+        & self,
+        dao_id: u64,
+        guild_id: u64,
+        address: String,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<bool, anyhow::Error> {
+        let who: AccountId32 = sr25519::Public::from_string(&address)
+            .map_err(|e| anyhow::anyhow!("invalid address {}: {:?}", address, e))?
+            .into();
+        let members = self.member_list(dao_id, guild_id, at).await?;
+        Ok(members.contains(&who))
     }
 
     /// 创建工会
@@ -54,8 +122,8 @@ impl WeteeGuild {
         name: String,
         desc: String,
         meta_data: String,
-        ext: Option<WithGov>,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
         let who: AccountId32 = sr25519::Public::from_string(&from).unwrap().into();
         let call = RuntimeCall::WeteeGuild(WeteeGuildCall::create_guild {
@@ -66,11 +134,7 @@ impl WeteeGuild {
             creator: who,
         });
 
-        if ext.is_some() {
-            return run_sudo_or_gov(&self.base, from, dao_id, call, ext.unwrap()).await;
-        }
-
-        self.base.send_and_sign(call,from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     // 成员列表
@@ -78,10 +142,11 @@ impl WeteeGuild {
         &self,
         dao_id: u64,
         guild_id: u64,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Vec<AccountId>, anyhow::Error> {
         // 构建请求 
         let result: Vec<AccountId> = self.base
-            .get_storage_double_map("WeteeOrg", "GuildMembers", QueryKey::U64Key(dao_id), QueryKey::U64Key(guild_id)).await
+            .get_storage_double_map("WeteeOrg", "GuildMembers", QueryKey::U64Key(dao_id), QueryKey::U64Key(guild_id), at).await
             .unwrap()
             .unwrap_or_else(|| vec![]);
 
@@ -94,8 +159,8 @@ impl WeteeGuild {
         from: String,
         dao_id: u64,
         guild_id: u64,
-        ext: Option<WithGov>,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
         let who: AccountId32 = sr25519::Public::from_string(&from).unwrap().into();
         let call = RuntimeCall::WeteeGuild(WeteeGuildCall::guild_join_request {
@@ -103,10 +168,6 @@ impl WeteeGuild {
             guild_id,
             who,
         });
-        if ext.is_some() {
-            return run_sudo_or_gov(&self.base, from, dao_id, call, ext.unwrap()).await;
-        }
-
-        self.base.send_and_sign(call,from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 }
@@ -1,12 +1,27 @@
-use super::{super::client::Client, wetee_gov::run_sudo_or_gov};
-use crate::model::{chain::QueryKey, dao::Quarter};
+use super::{super::client::Client, wetee_gov::execute};
+use crate::batch::{BatchItem, StorageBatch};
+use crate::client::storage_key_suffix;
+use crate::indexer::load_dao_state;
+use crate::hander::balance::AccountData;
+use crate::model::account::AssetAccountData;
+use crate::model::{chain::{BlockRef, QueryKey, StorageQuery, StorageStream}, dao::{DaoOverview, DaoSummary, ProjectTaskCount, Quarter, RoadmapId, ROADMAP_TASK_DONE}};
+use crate::model::snapshot::{
+    DaoSnapshot, SnapshotGuild, SnapshotMember, SnapshotProject, SnapshotReferendum,
+    SnapshotRoadmap, SNAPSHOT_VERSION,
+};
 
-use crate::model::dao::WithGov;
+use crate::chain::DECIMALS;
+use crate::model::amount::Amount;
+use crate::model::dao::{Executed, Execution};
 use sp_core::{crypto::Ss58Codec, sr25519};
 use sp_runtime::AccountId32;
 pub use wetee_org::{App, OrgApp};
 pub use wetee_org::{OrgInfo, QuarterTask, Status};
-use wetee_runtime::{AccountId, BlockNumber, RuntimeCall, WeteeOrgCall, WeteeAssetsCall};
+use wetee_assets::{DaoAssetInfo, DaoAssetMeta};
+use substrate_api_client::ac_primitives::AccountInfo;
+use wetee_gov::ReferendumStatus;
+use wetee_project::TaskStatus;
+use wetee_runtime::{AccountId, BlockNumber, Nonce, RuntimeCall, WeteeOrgCall, WeteeAssetsCall};
 
 /// DAO 模块
 pub struct WeteeOrg {
@@ -19,11 +34,14 @@ impl WeteeOrg {
     }
 
     // 下一个 DAO ID
-    pub async fn next_dao_id(&mut self) -> anyhow::Result<u64, anyhow::Error> {
+    pub async fn next_dao_id(
+        &mut self,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<u64, anyhow::Error> {
         // 构建请求
         let result: u64 = self
             .base
-            .get_storage_value("WeteeOrg", "NextDaoId")
+            .get_storage_value("WeteeOrg", "NextDaoId", at)
             .await
             .unwrap()
             .unwrap_or_else(|| 5000);
@@ -62,10 +80,11 @@ impl WeteeOrg {
     // DAO 组织
     pub async fn orgs(
        & self,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Vec<(String, OrgInfo<AccountId, u64>)>, anyhow::Error> {
         let results: Vec<(String, OrgInfo<AccountId, u64>)> = self
             .base
-            .get_storage_map_all("WeteeOrg", "Daos")
+            .get_storage_map_all("WeteeOrg", "Daos", at)
             .await
             .unwrap();
 
@@ -76,11 +95,12 @@ impl WeteeOrg {
     pub async fn member_list(
        & self,
         dao_id: u64,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Vec<AccountId>, anyhow::Error> {
         // 构建请求
         let result: Vec<AccountId> = self
             .base
-            .get_storage_map("WeteeOrg", "Members", QueryKey::U64Key(dao_id))
+            .get_storage_map("WeteeOrg", "Members", QueryKey::U64Key(dao_id), at)
             .await
             .unwrap()
             .unwrap_or_else(|| vec![]);
@@ -88,11 +108,26 @@ impl WeteeOrg {
         Ok(result)
     }
 
+    // 订阅成员列表变化
+    pub async fn watch_members(
+       & self,
+        dao_id: u64,
+    ) -> anyhow::Result<StorageStream<Vec<AccountId>>, anyhow::Error> {
+        self.base
+            .watch_storage(StorageQuery::Map {
+                storage_prefix: "WeteeOrg",
+                storage_key_name: "Members",
+                key: QueryKey::U64Key(dao_id),
+            })
+            .await
+    }
+
     // 成员声誉
     pub async fn member_point(
        & self,
         dao_id: u64,
         member: String,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<u32, anyhow::Error> {
         // 构建请求
         let who: AccountId32 = sr25519::Public::from_string(&member).unwrap().into();
@@ -103,6 +138,7 @@ impl WeteeOrg {
                 "MemberPoint",
                 QueryKey::U64Key(dao_id),
                 QueryKey::AccountId(who),
+                at,
             )
             .await
             .unwrap()
@@ -115,11 +151,12 @@ impl WeteeOrg {
     pub async fn dao_info(
        & self,
         dao_id: u64,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<OrgInfo<AccountId, BlockNumber>, anyhow::Error> {
         // 构建请求
         let result: OrgInfo<AccountId, BlockNumber> = self
             .base
-            .get_storage_map("WeteeOrg", "Daos", QueryKey::U64Key(dao_id))
+            .get_storage_map("WeteeOrg", "Daos", QueryKey::U64Key(dao_id), at)
             .await
             .unwrap()
             .unwrap();
@@ -127,18 +164,179 @@ impl WeteeOrg {
         Ok(result)
     }
 
-    // 加入 DAO
+    // DAO 概要，一次请求获取信息、成员、发行总量和应用
+    pub async fn dao_summary(
+       & self,
+        dao_id: u64,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<DaoSummary, anyhow::Error> {
+        let mut batch = StorageBatch::new();
+        let info = batch.map("WeteeOrg", "Daos", QueryKey::U64Key(dao_id));
+        let members = batch.map("WeteeOrg", "Members", QueryKey::U64Key(dao_id));
+        let total_issuance = batch.map("Tokens", "TotalIssuance", QueryKey::U64Key(dao_id));
+        let apps = batch.map("WeteeOrg", "OrgApps", QueryKey::U64Key(dao_id));
+        let result = batch.fetch(&self.base, at).await?;
+
+        Ok(DaoSummary {
+            info: result
+                .get(&info)?
+                .ok_or_else(|| anyhow::anyhow!("dao {} not found", dao_id))?,
+            members: result.get_or_default(&members)?,
+            total_issuance: result.get_or_default(&total_issuance)?,
+            apps: result.get_or_default(&apps)?,
+        })
+    }
+
+    // DAO 总览，包含统计、国库余额、进行中的投票、项目任务和声誉排行
+    pub async fn overview(
+       & self,
+        dao_id: u64,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<DaoOverview, anyhow::Error> {
+        let state = load_dao_state(&self.base, dao_id, at)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("dao {} not found", dao_id))?;
+
+        let mut batch = StorageBatch::new();
+        let total_issuance = batch.map("Tokens", "TotalIssuance", QueryKey::U64Key(dao_id));
+        let treasury = batch.map::<AccountInfo<Nonce, AccountData<u128>>>(
+            "System",
+            "Account",
+            QueryKey::AccountId(state.info.dao_account_id.clone()),
+        );
+        let treasury_asset = batch.double_map(
+            "Tokens",
+            "Accounts",
+            QueryKey::AccountId(state.info.dao_account_id.clone()),
+            QueryKey::U64Key(dao_id),
+        );
+        let result = batch.fetch(&self.base, at).await?;
+        let treasury = result.get(&treasury)?.unwrap_or_default();
+
+        let mut leaderboard = state.members;
+        leaderboard.sort_by(|a, b| b.1.cmp(&a.1));
+
+        Ok(DaoOverview {
+            member_count: leaderboard.len() as u32,
+            guild_count: state.guilds.len() as u32,
+            project_count: state.projects.len() as u32,
+            total_issuance: result.get_or_default(&total_issuance)?,
+            treasury: AssetAccountData {
+                free: treasury.data.free,
+                frozen: treasury.data.frozen,
+                reserved: treasury.data.reserved,
+            },
+            treasury_asset: result.get_or_default(&treasury_asset)?,
+            active_referendums: state
+                .referendums
+                .iter()
+                .filter(|(_, r)| matches!(r.status, ReferendumStatus::Ongoing))
+                .map(|(index, _)| *index)
+                .collect(),
+            project_tasks: state
+                .projects
+                .iter()
+                .map(|(project, _, tasks)| ProjectTaskCount {
+                    project_id: project.id,
+                    open_tasks: tasks
+                        .iter()
+                        .filter(|t| !matches!(t.status, TaskStatus::Done))
+                        .count() as u32,
+                    total_tasks: tasks.len() as u32,
+                })
+                .collect(),
+            leaderboard,
+            info: state.info,
+        })
+    }
+
+    // DAO 快照，包含成员、公会、项目、任务、里程碑、资产、应用和进行中的投票
+    // at 为 None 时使用最新确认的区块
+    pub async fn snapshot(
+       & self,
+        dao_id: u64,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<DaoSnapshot, anyhow::Error> {
+        let at = match at {
+            Some(at) => at,
+            None => BlockRef::Number(self.base.get_block_number().await?.try_into()?),
+        };
+        let state = load_dao_state(&self.base, dao_id, Some(at))
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("dao {} not found", dao_id))?;
+
+        let mut batch = StorageBatch::new();
+        let asset = batch.map::<DaoAssetInfo<AccountId, DaoAssetMeta>>(
+            "WeteeAsset",
+            "DaoAssetsInfo",
+            QueryKey::U64Key(dao_id),
+        );
+        let total_issuance = batch.map("Tokens", "TotalIssuance", QueryKey::U64Key(dao_id));
+        let apps = batch.map("WeteeOrg", "OrgApps", QueryKey::U64Key(dao_id));
+        let result = batch.fetch(&self.base, Some(at)).await?;
+
+        let roadmaps: Vec<(String, Vec<QuarterTask<AccountId>>)> = self
+            .base
+            .get_storage_double_map_first("WeteeOrg", "RoadMaps", QueryKey::U64Key(dao_id), Some(at))
+            .await?;
+        let mut roadmap_list = vec![];
+        for (key, tasks) in roadmaps.into_iter() {
+            roadmap_list.push(SnapshotRoadmap {
+                roadmap_id: storage_key_suffix::<RoadmapId>(&key, 4)?,
+                tasks,
+            });
+        }
+        roadmap_list.sort_by_key(|r| r.roadmap_id);
+
+        Ok(DaoSnapshot {
+            version: SNAPSHOT_VERSION,
+            dao_id,
+            at,
+            info: state.info,
+            members: state
+                .members
+                .into_iter()
+                .map(|(account, point)| SnapshotMember { account, point })
+                .collect(),
+            guilds: state
+                .guilds
+                .into_iter()
+                .map(|(info, members)| SnapshotGuild { info, members })
+                .collect(),
+            projects: state
+                .projects
+                .into_iter()
+                .map(|(info, members, tasks)| SnapshotProject {
+                    info,
+                    members,
+                    tasks,
+                })
+                .collect(),
+            roadmaps: roadmap_list,
+            asset: result.get(&asset)?.map(|a| a.metadata),
+            total_issuance: result.get_or_default(&total_issuance)?,
+            apps: result.get_or_default(&apps)?,
+            referendums: state
+                .referendums
+                .into_iter()
+                .filter(|(_, r)| matches!(r.status, ReferendumStatus::Ongoing))
+                .map(|(index, referendum)| SnapshotReferendum { index, referendum })
+                .collect(),
+        })
+    }
+
+    // 加入 DAO，加入的账户为签名账户，只能直接签名执行
     pub async fn join(
        & self,
         from: String,
         dao_id: u64,
         share_expect: u32,
-        value: u64,
+        value: Amount,
     ) -> anyhow::Result<(), anyhow::Error> {
         let call = RuntimeCall::WeteeAsset(WeteeAssetsCall::join_request {
             dao_id,
             share_expect,
-            existenial_deposit: value.into(),
+            existenial_deposit: value.ensure_decimals(DECIMALS)?,
         });
         self.base.send_and_sign(call, from).await
     }
@@ -148,56 +346,212 @@ impl WeteeOrg {
        & self,
         dao_id: u64,
         year: u32,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Vec<Quarter>, anyhow::Error> {
-        let mut results = vec![];
-        for quarter in 1..5 {
-            let tasks: Vec<QuarterTask<AccountId>> = self
-                .base
-                .get_storage_double_map(
-                    "WeteeOrg",
-                    "RoadMaps",
-                    QueryKey::U64Key(dao_id),
-                    QueryKey::U32Key((year * 100 + quarter).into()),
-                )
-                .await
-                .unwrap()
-                .unwrap_or_else(|| vec![]);
+        self.roadmap_range(dao_id, RoadmapId::new(year, 1)?, RoadmapId::new(year, 4)?, &[], at)
+            .await
+    }
 
-            results.push(Quarter {
-                year,
-                quarter,
-                tasks,
-            });
+    // 多个季度的里程碑，包含 from 和 to，tags 不为空时只返回包含任一标签的任务
+    pub async fn roadmap_range(
+       & self,
+        dao_id: u64,
+        from: RoadmapId,
+        to: RoadmapId,
+        tags: &[u8],
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Vec<Quarter>, anyhow::Error> {
+        let mut batch = StorageBatch::new();
+        let items: Vec<(RoadmapId, BatchItem<Vec<QuarterTask<AccountId>>>)> =
+            RoadmapId::range(from, to)
+                .into_iter()
+                .map(|id| {
+                    (
+                        id,
+                        batch.double_map(
+                            "WeteeOrg",
+                            "RoadMaps",
+                            QueryKey::U64Key(dao_id),
+                            QueryKey::U32Key(u32::from(id)),
+                        ),
+                    )
+                })
+                .collect();
+        let result = batch.fetch(&self.base, at).await?;
+
+        let mut results = vec![];
+        for (id, item) in items.iter() {
+            let quarter = Quarter {
+                year: id.year(),
+                quarter: id.quarter(),
+                tasks: result.get_or_default(item)?,
+            };
+            results.push(quarter.filter_tags(tags));
         }
 
         Ok(results)
     }
 
+    // 里程碑任务
+    pub async fn roadmap_task(
+       & self,
+        dao_id: u64,
+        roadmap_id: RoadmapId,
+        task_id: u64,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Option<QuarterTask<AccountId>>, anyhow::Error> {
+        let tasks: Vec<QuarterTask<AccountId>> = self
+            .base
+            .get_storage_double_map(
+                "WeteeOrg",
+                "RoadMaps",
+                QueryKey::U64Key(dao_id),
+                QueryKey::U32Key(u32::from(roadmap_id)),
+                at,
+            )
+            .await?
+            .unwrap_or_default();
+
+        Ok(tasks.into_iter().find(|t| t.id == task_id))
+    }
+
     // 创建任务
     pub async fn create_task(
        & self,
         from: String,
         dao_id: u64,
-        roadmap_id: u32,
+        roadmap_id: RoadmapId,
         name: Vec<u8>,
         priority: u8,
         tags: Option<Vec<u8>>,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         let call = RuntimeCall::WeteeOrg(WeteeOrgCall::create_roadmap_task {
             dao_id,
-            roadmap_id,
+            roadmap_id: roadmap_id.into(),
             name,
             priority,
             tags,
         });
-        self.base.send_and_sign(call, from).await
+        execute(&self.base, from, dao_id, call, exec).await
+    }
+
+    // 更新任务状态、优先级和标签
+    pub async fn update_task(
+       & self,
+        from: String,
+        dao_id: u64,
+        roadmap_id: RoadmapId,
+        task_id: u64,
+        priority: u8,
+        status: u8,
+        tags: Option<Vec<u8>>,
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
+        let call = RuntimeCall::WeteeOrg(WeteeOrgCall::update_roadmap_task {
+            dao_id,
+            roadmap_id: roadmap_id.into(),
+            task_id,
+            priority,
+            status,
+            tags,
+        });
+        execute(&self.base, from, dao_id, call, exec).await
+    }
+
+    // 完成任务
+    pub async fn complete_task(
+       & self,
+        from: String,
+        dao_id: u64,
+        roadmap_id: RoadmapId,
+        task_id: u64,
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
+        let task = self
+            .roadmap_task(dao_id, roadmap_id, task_id, None)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("roadmap task {} not found in {}", task_id, roadmap_id))?;
+        self.update_task(
+            from,
+            dao_id,
+            roadmap_id,
+            task_id,
+            task.priority,
+            ROADMAP_TASK_DONE,
+            Some(task.tags),
+            exec,
+        )
+        .await
+    }
+
+    // 复制任务到其他季度，只提交一个创建调用，原任务保持不变
+    pub async fn copy_task(
+       & self,
+        from: String,
+        dao_id: u64,
+        source: RoadmapId,
+        task_id: u64,
+        target: RoadmapId,
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
+        let task = self
+            .roadmap_task(dao_id, source, task_id, None)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("roadmap task {} not found in {}", task_id, source))?;
+        self.create_task(
+            from,
+            dao_id,
+            target,
+            task.name,
+            task.priority,
+            Some(task.tags),
+            exec,
+        )
+        .await
+    }
+
+    // 移动任务到其他季度，返回创建新任务和完成原任务两个调用的执行结果
+    // 链上没有移动或删除任务的调用，这里在目标季度创建任务后把原任务标记为已完成
+    // runtime 没有批量调用，两个调用分别提交，不是原子操作：
+    // 第二个调用失败时新任务已经创建，原任务仍未完成，通过治理执行时会创建两个提案
+    pub async fn move_task(
+       & self,
+        from: String,
+        dao_id: u64,
+        source: RoadmapId,
+        task_id: u64,
+        target: RoadmapId,
+        exec: Execution,
+    ) -> anyhow::Result<(Executed, Executed), anyhow::Error> {
+        let copied = self
+            .copy_task(from.clone(), dao_id, source, task_id, target, exec.clone())
+            .await?;
+        let completed = self
+            .complete_task(from, dao_id, source, task_id, exec)
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "roadmap task {} was copied to {} but completing it in {} failed: {}",
+                    task_id,
+                    target,
+                    source,
+                    e
+                )
+            })?;
+
+        Ok((copied, completed))
     }
 
     // DAO 发行货币总量
-    pub async fn total_issuance(& self, dao_id: u64) -> anyhow::Result<u128, anyhow::Error> {
+    pub async fn total_issuance(
+       & self,
+        dao_id: u64,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<u128, anyhow::Error> {
         let result: u128 = self
             .base
-            .get_storage_map("Tokens", "TotalIssuance", QueryKey::U64Key(dao_id))
+            .get_storage_map("Tokens", "TotalIssuance", QueryKey::U64Key(dao_id), at)
             .await
             .unwrap()
             .unwrap_or_else(|| 0);
@@ -205,7 +559,7 @@ impl WeteeOrg {
         Ok(result)
     }
 
-    // 创建应用
+    // 创建应用，应用不属于 DAO，只能由签名账户直接执行
     pub async fn create_app(
        & self,
         from: String,
@@ -213,23 +567,24 @@ impl WeteeOrg {
         desc: String,
         icon: String,
         url: String,
-    ) -> anyhow::Result<(), anyhow::Error> {
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         let call = RuntimeCall::WeteeOrg(WeteeOrgCall::create_app {
             name: name.into(),
             desc: desc.into(),
             icon: icon.into(),
             url: url.into(),
         });
-        self.base.send_and_sign(call, from).await
+        let block_hash = self.base.send_and_sign_in_block(call, from).await?;
+        Ok(Executed::Immediately { block_hash })
     }
 
-    // 应用状态
+    // 应用状态，只能由签名账户直接执行
     pub async fn update_app_status(
        & self,
         from: String,
         app_id: u64,
         status: u8,
-    ) -> anyhow::Result<(), anyhow::Error> {
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         let call = RuntimeCall::WeteeOrg(WeteeOrgCall::update_app_status {
             app_id,
             status: if status == 0 {
@@ -238,7 +593,8 @@ impl WeteeOrg {
                 Status::InActive
             },
         });
-        self.base.send_and_sign(call, from).await
+        let block_hash = self.base.send_and_sign_in_block(call, from).await?;
+        Ok(Executed::Immediately { block_hash })
     }
 
     // 应用集成
@@ -247,13 +603,10 @@ impl WeteeOrg {
         from: String,
         dao_id: u64,
         app_id: u64,
-        ext: Option<WithGov>,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         let call = RuntimeCall::WeteeOrg(WeteeOrgCall::org_integrate_app { dao_id, app_id });
-        if ext.is_some() {
-            return run_sudo_or_gov(&self.base, from, dao_id, call, ext.unwrap()).await;
-        }
-        self.base.send_and_sign(call, from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     // 更新应用状态
@@ -263,8 +616,8 @@ impl WeteeOrg {
         dao_id: u64,
         app_id: u64,
         status: u8,
-        ext: Option<WithGov>,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         let call = RuntimeCall::WeteeOrg(WeteeOrgCall::update_org_app_status {
             dao_id,
             app_id,
@@ -274,19 +627,17 @@ impl WeteeOrg {
                 Status::InActive
             },
         });
-        if ext.is_some() {
-            return run_sudo_or_gov(&self.base, from, dao_id, call, ext.unwrap()).await;
-        }
-        self.base.send_and_sign(call, from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     // DAO 应用
     pub async fn app_hubs(
        & self,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Vec<(String, App<AccountId>)>, anyhow::Error> {
         let results: Vec<(String, App<AccountId>)> = self
             .base
-            .get_storage_map_all("WeteeOrg", "AppHubs")
+            .get_storage_map_all("WeteeOrg", "AppHubs", at)
             .await
             .unwrap();
 
@@ -297,10 +648,11 @@ impl WeteeOrg {
     pub async fn org_apps(
        & self,
         dao_id: u64,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Vec<OrgApp<BlockNumber>>, anyhow::Error> {
         let result: Vec<OrgApp<BlockNumber>> = self
             .base
-            .get_storage_map("WeteeOrg", "OrgApps", QueryKey::U64Key(dao_id))
+            .get_storage_map("WeteeOrg", "OrgApps", QueryKey::U64Key(dao_id), at)
             .await
             .unwrap()
             .unwrap_or_else(|| vec![]);
@@ -1,18 +1,28 @@
-use crate::model::chain::QueryKey;
-use crate::model::dao::WithGov;
+use crate::batch::StorageBatch;
+use crate::client::storage_key_suffix;
+use crate::model::chain::{BlockRef, QueryKey, StorageQuery, StorageStream};
+use crate::model::dao::{Executed, Execution};
+use crate::indexer::store::IndexStore;
+use crate::model::account::AssetAccountData;
+use crate::model::amount::Amount;
+use crate::model::finance::ProjectFinance;
+use crate::model::task::{TaskHit, TaskPage, TaskQuery, TaskReview, TaskView};
 use crate::Client;
 
 
-use super::wetee_gov::run_sudo_or_gov;
+use super::wetee_asset::asset_decimals;
+use super::wetee_gov::execute;
 use sp_core::sr25519;
 use sp_core::{crypto::Ss58Codec, sr25519::Public};
 use sp_runtime::AccountId32;
-use wetee_gov::MemmberData;
 use wetee_project::ReviewOpinion;
 pub use wetee_project::{ProjectInfo, TaskInfo, TaskStatus};
 use wetee_runtime::{AccountId, Balance, RuntimeCall, WeteeProjectCall};
 
 /// 账户
+/// 链上 WeteeProject 只提供创建项目、加入项目、任务流程和项目资金相关的调用，
+/// 修改项目信息、关闭项目、移除成员、修改任务字段、重新指定执行人或评审以及取消任务
+/// 在运行时中都没有对应调用，这里不提供
 pub struct WeteeProject {
     pub base: Client,
 }
@@ -26,9 +36,10 @@ impl WeteeProject {
     pub async fn project_list(
         & self,
         dao_id: u64,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Vec<ProjectInfo<AccountId>>, anyhow::Error> {
         // 构建请求
-        let result: Vec<ProjectInfo<AccountId>> = self.base.get_storage_map("WeteeProject", "DaoProjects", QueryKey::U64Key(dao_id)).await
+        let result: Vec<ProjectInfo<AccountId>> = self.base.get_storage_map("WeteeProject", "DaoProjects", QueryKey::U64Key(dao_id), at).await
             .unwrap()
             .unwrap_or_else(|| vec![]);
 
@@ -42,8 +53,8 @@ impl WeteeProject {
         dao_id: u64,
         name: String,
         desc: String,
-        ext: Option<WithGov>,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
         let call = RuntimeCall::WeteeProject(WeteeProjectCall::create_project {
             name: name.into(),
@@ -52,11 +63,7 @@ impl WeteeProject {
             creator: AccountId::from(Public::from_string(&from).unwrap()),
         });
 
-        if ext.is_some() {
-            return run_sudo_or_gov(&self.base, from, dao_id, call, ext.unwrap()).await;
-        }
-
-        self.base.send_and_sign(call,from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     pub async fn project_join_request_with_root(
@@ -65,7 +72,7 @@ impl WeteeProject {
         dao_id: u64,
         project_id: u64,
         user: String,
-    ) -> anyhow::Result<(), anyhow::Error> {
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
         let who: AccountId32 = sr25519::Public::from_string(&user).unwrap().into();
         let call = RuntimeCall::WeteeProject(WeteeProjectCall::project_join_request {
@@ -74,18 +81,7 @@ impl WeteeProject {
             who,
         });
 
-        return run_sudo_or_gov(
-            &self.base,
-            from,
-            dao_id,
-            call,
-            WithGov {
-                run_type: 2,
-                amount: 0,
-                member: MemmberData::GLOBAL,
-                period_index: 0,
-            },
-        ).await;
+        execute(&self.base, from, dao_id, call, Execution::Sudo).await
     }
 
     pub async fn project_join_request(
@@ -93,8 +89,8 @@ impl WeteeProject {
         from: String,
         dao_id: u64,
         project_id: u64,
-        ext: Option<WithGov>,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
         let who: AccountId32 = sr25519::Public::from_string(&from).unwrap().into();
         let call = RuntimeCall::WeteeProject(WeteeProjectCall::project_join_request {
@@ -103,11 +99,7 @@ impl WeteeProject {
             who,
         });
 
-        if ext.is_some() {
-            return run_sudo_or_gov(&self.base, from, dao_id, call, ext.unwrap()).await;
-        }
-
-        self.base.send_and_sign(call,from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     // 成员列表
@@ -115,10 +107,11 @@ impl WeteeProject {
         & self,
         dao_id: u64,
         project_id: u64,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Vec<AccountId>, anyhow::Error> {
         // 构建请求
         let result: Vec<AccountId> = self.base
-            .get_storage_double_map("WeteeOrg", "ProjectMembers", QueryKey::U64Key(dao_id), QueryKey::U64Key(project_id)).await
+            .get_storage_double_map("WeteeOrg", "ProjectMembers", QueryKey::U64Key(dao_id), QueryKey::U64Key(project_id), at).await
             .unwrap()
             .unwrap_or_else(|| vec![]);
 
@@ -129,29 +122,172 @@ impl WeteeProject {
     pub async fn task_list(
         & self,
         project_id: u64,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<Vec<TaskInfo<AccountId, Balance>>, anyhow::Error> {
         // 构建请求
-        let result: Vec<TaskInfo<AccountId, Balance>> = self.base.get_storage_map("WeteeProject", "Tasks", QueryKey::U64Key(project_id)).await
+        let result: Vec<TaskInfo<AccountId, Balance>> = self.base.get_storage_map("WeteeProject", "Tasks", QueryKey::U64Key(project_id), at).await
             .unwrap()
             .unwrap_or_else(|| vec![]);
 
         Ok(result)
     }
 
+    // 订阅任务列表变化
+    pub async fn watch_tasks(
+        & self,
+        project_id: u64,
+    ) -> anyhow::Result<StorageStream<Vec<TaskInfo<AccountId, Balance>>>, anyhow::Error> {
+        self.base
+            .watch_storage(StorageQuery::Map {
+                storage_prefix: "WeteeProject",
+                storage_key_name: "Tasks",
+                key: QueryKey::U64Key(project_id),
+            })
+            .await
+    }
+
+    // 任务信息，Tasks 以项目为单位保存为一个列表，会下载整个列表
+    // 需要频繁查询时使用索引 IndexStore::task
     pub async fn task_info(
         & self,
         project_id: u64,
         task_id: u64,
+        at: Option<BlockRef>,
     ) -> anyhow::Result<TaskInfo<AccountId, Balance>, anyhow::Error> {
-        // 构建请求
-        let result: Vec<TaskInfo<AccountId, Balance>> = self.base.get_storage_map("WeteeProject", "Tasks", QueryKey::U64Key(project_id)).await
-            .unwrap()
-            .unwrap_or_else(|| vec![]);
-        let task = result
-            .into_iter()
-            .find(|x| x.id == task_id)
-            .ok_or_else(|| anyhow::anyhow!("task not found"))?;
-        Ok(task)
+        let mut result = self.task_list(project_id, at).await?;
+        // 任务按 ID 递增追加，先二分查找
+        let index = match result.binary_search_by_key(&task_id, |x| x.id) {
+            Ok(index) => Some(index),
+            Err(_) => result.iter().position(|x| x.id == task_id),
+        };
+        match index {
+            Some(index) => Ok(result.swap_remove(index)),
+            None => Err(anyhow::anyhow!("task {} not found in project {}", task_id, project_id)),
+        }
+    }
+
+    // 任务的评审记录
+    pub async fn task_reviews(
+        & self,
+        task_id: u64,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Vec<TaskReview>, anyhow::Error> {
+        let result: Vec<TaskReview> = self
+            .base
+            .get_storage_map("WeteeProject", "TaskReviews", QueryKey::U64Key(task_id), at)
+            .await?
+            .unwrap_or_default();
+        Ok(result)
+    }
+
+    // 查询 DAO 下所有项目的任务
+    pub async fn search_tasks(
+        & self,
+        dao_id: u64,
+        query: &TaskQuery,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<TaskPage, anyhow::Error> {
+        let tasks = tasks_of_daos(&self.base, &[dao_id], at).await?;
+        Ok(query.apply(tasks))
+    }
+
+    // 账户所在的所有 DAO 中，账户作为执行人或评审的任务
+    pub async fn my_tasks(
+        & self,
+        address: String,
+        query: &TaskQuery,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<TaskPage, anyhow::Error> {
+        let who: AccountId32 = sr25519::Public::from_string(&address)
+            .map_err(|e| anyhow::anyhow!("invalid address {}: {:?}", address, e))?
+            .into();
+        let members: Vec<(String, Vec<AccountId>)> = self
+            .base
+            .get_storage_map_all("WeteeOrg", "Members", at)
+            .await?;
+        let mut dao_ids = vec![];
+        for (key, list) in members.iter() {
+            if list.contains(&who) {
+                dao_ids.push(storage_key_suffix::<u64>(key, 8)?);
+            }
+        }
+
+        let tasks = tasks_of_daos(&self.base, &dao_ids, at).await?;
+        let mut query = query.clone();
+        query.involves = Some(who);
+        Ok(query.apply(tasks))
+    }
+
+    // 项目资金报表，store 不为 None 时统计已申请的资金
+    pub async fn finance(
+        & self,
+        dao_id: u64,
+        project_id: u64,
+        store: Option<&IndexStore>,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<ProjectFinance, anyhow::Error> {
+        let project = self.project_info(dao_id, project_id, at).await?;
+        self.project_finance(dao_id, project, store, at).await
+    }
+
+    // DAO 所有项目的资金报表
+    pub async fn dao_finance(
+        & self,
+        dao_id: u64,
+        store: Option<&IndexStore>,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<Vec<ProjectFinance>, anyhow::Error> {
+        let mut results = vec![];
+        for project in self.project_list(dao_id, at).await?.into_iter() {
+            results.push(self.project_finance(dao_id, project, store, at).await?);
+        }
+        Ok(results)
+    }
+
+    async fn project_finance(
+        & self,
+        dao_id: u64,
+        project: ProjectInfo<AccountId>,
+        store: Option<&IndexStore>,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<ProjectFinance, anyhow::Error> {
+        let mut batch = StorageBatch::new();
+        let tasks = batch.map::<Vec<TaskInfo<AccountId, Balance>>>(
+            "WeteeProject",
+            "Tasks",
+            QueryKey::U64Key(project.id),
+        );
+        let balance = batch.double_map::<AssetAccountData<u128>>(
+            "Tokens",
+            "Accounts",
+            QueryKey::AccountId(project.project_account_id.clone()),
+            QueryKey::U64Key(dao_id),
+        );
+        let result = batch.fetch(&self.base, at).await?;
+
+        let funds_applied = match store {
+            Some(store) => {
+                let mut total: u128 = 0;
+                for fund in store.project_funds(dao_id, project.id).await?.iter() {
+                    total = total.saturating_add(fund.amount.parse()?);
+                }
+                Some(total)
+            }
+            None => None,
+        };
+
+        Ok(ProjectFinance::build(
+            dao_id,
+            project.id,
+            project.project_account_id,
+            &result
+                .get_or_default(&tasks)?
+                .iter()
+                .map(TaskView::from)
+                .collect::<Vec<_>>(),
+            result.get_or_default(&balance)?,
+            funds_applied,
+        ))
     }
 
     // 创建任务
@@ -168,9 +304,11 @@ impl WeteeProject {
         reviewers: Option<Vec<String>>,
         skills: Option<Vec<u8>>,
         max_assignee: Option<u8>,
-        amount: u128,
-    ) -> anyhow::Result<(), anyhow::Error> {
-        // 构建请求
+        amount: Amount,
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
+        // 构建请求，数量为 DAO 代币
+        let amount = amount.ensure_decimals(asset_decimals(&self.base, dao_id).await?)?;
         let call = RuntimeCall::WeteeProject(WeteeProjectCall::create_task {
             name: name.into(),
             description: desc.into(),
@@ -180,33 +318,12 @@ impl WeteeProject {
             priority,
             max_assignee,
             skills,
-            assignees: if assignees.is_some() {
-                Some(
-                    assignees
-                        .unwrap()
-                        .into_iter()
-                        .map(|x| AccountId::from(Public::from_string(&x).unwrap()))
-                        .collect(),
-                )
-            } else {
-                None
-            },
-            reviewers: if reviewers.is_some() {
-                Some(
-                    reviewers
-                        .unwrap()
-                        .into_iter()
-                        .map(|x| AccountId::from(Public::from_string(&x).unwrap()))
-                        .collect(),
-                )
-            } else {
-                None
-            },
+            assignees: assignees.map(to_accounts).transpose()?,
+            reviewers: reviewers.map(to_accounts).transpose()?,
             amount,
         });
 
-        
-        self.base.send_and_sign(call,from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     /// 开始任务
@@ -216,7 +333,8 @@ impl WeteeProject {
         dao_id: u64,
         project_id: u64,
         task_id: u64,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
         let call = RuntimeCall::WeteeProject(WeteeProjectCall::start_task {
             dao_id,
@@ -224,7 +342,7 @@ impl WeteeProject {
             task_id,
         });
 
-        self.base.send_and_sign(call,from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     pub async fn request_review(
@@ -233,7 +351,8 @@ impl WeteeProject {
         dao_id: u64,
         project_id: u64,
         task_id: u64,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
         let call = RuntimeCall::WeteeProject(WeteeProjectCall::request_review {
             dao_id,
@@ -242,7 +361,7 @@ impl WeteeProject {
         });
 
 
-        self.base.send_and_sign(call,from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     // 完成任务
@@ -252,7 +371,8 @@ impl WeteeProject {
         dao_id: u64,
         project_id: u64,
         task_id: u64,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
         let call = RuntimeCall::WeteeProject(WeteeProjectCall::task_done {
             dao_id,
@@ -260,7 +380,7 @@ impl WeteeProject {
             task_id,
         });
 
-        self.base.send_and_sign(call,from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     // 加入任务
@@ -270,7 +390,8 @@ impl WeteeProject {
         dao_id: u64,
         project_id: u64,
         task_id: u64,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
         let call = RuntimeCall::WeteeProject(WeteeProjectCall::join_task {
             dao_id,
@@ -278,7 +399,7 @@ impl WeteeProject {
             task_id,
         });
 
-        self.base.send_and_sign(call,from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     // 离开任务
@@ -288,7 +409,8 @@ impl WeteeProject {
         dao_id: u64,
         project_id: u64,
         task_id: u64,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
         let call = RuntimeCall::WeteeProject(WeteeProjectCall::leave_task {
             dao_id,
@@ -296,7 +418,7 @@ impl WeteeProject {
             task_id,
         });
 
-        self.base.send_and_sign(call,from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     // 作为任务评审
@@ -306,7 +428,8 @@ impl WeteeProject {
         dao_id: u64,
         project_id: u64,
         task_id: u64,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
         let call = RuntimeCall::WeteeProject(WeteeProjectCall::join_task_review {
             dao_id,
@@ -314,7 +437,7 @@ impl WeteeProject {
             task_id,
         });
 
-        self.base.send_and_sign(call,from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     // 离开任务评审
@@ -324,7 +447,8 @@ impl WeteeProject {
         dao_id: u64,
         project_id: u64,
         task_id: u64,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
         let call = RuntimeCall::WeteeProject(WeteeProjectCall::leave_task_review {
             dao_id,
@@ -332,7 +456,7 @@ impl WeteeProject {
             task_id,
         });
 
-        self.base.send_and_sign(call,from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     pub async fn make_review(
@@ -343,7 +467,8 @@ impl WeteeProject {
         task_id: u64,
         approve: bool,
         meta: String,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
         let call = RuntimeCall::WeteeProject(WeteeProjectCall::make_review {
             dao_id,
@@ -357,7 +482,7 @@ impl WeteeProject {
             meta: meta.into(),
         });
 
-        self.base.send_and_sign(call,from).await
+        execute(&self.base, from, dao_id, call, exec).await
     }
 
     pub async fn apply_project_funds(
@@ -365,20 +490,95 @@ impl WeteeProject {
         from: String,
         dao_id: u64,
         project_id: u64,
-        amount: u64,
-        ext: Option<WithGov>,
-    ) -> anyhow::Result<(), anyhow::Error> {
+        amount: Amount,
+        exec: Execution,
+    ) -> anyhow::Result<Executed, anyhow::Error> {
         // 构建请求
+        let amount = amount.ensure_decimals(asset_decimals(&self.base, dao_id).await?)?;
         let call = RuntimeCall::WeteeProject(WeteeProjectCall::apply_project_funds {
             dao_id,
             project_id,
-            amount: amount.into(),
+            amount,
         });
 
-        if ext.is_some() {
-            return run_sudo_or_gov(&self.base, from, dao_id, call, ext.unwrap()).await;
-        }
+        execute(&self.base, from, dao_id, call, exec).await
+    }
+
+    // 项目信息
+    pub async fn project_info(
+        & self,
+        dao_id: u64,
+        project_id: u64,
+        at: Option<BlockRef>,
+    ) -> anyhow::Result<ProjectInfo<AccountId>, anyhow::Error> {
+        let result = self.project_list(dao_id, at).await?;
+        result
+            .into_iter()
+            .find(|x| x.id == project_id)
+            .ok_or_else(|| anyhow::anyhow!("project not found"))
+    }
+}
+
+// 解析 SS58 地址列表，任一地址无效时返回错误
+fn to_accounts(addresses: Vec<String>) -> anyhow::Result<Vec<AccountId>> {
+    addresses
+        .into_iter()
+        .map(|x| {
+            Public::from_string(&x)
+                .map(AccountId::from)
+                .map_err(|e| anyhow::anyhow!("invalid address {}: {:?}", x, e))
+        })
+        .collect()
+}
 
-        self.base.send_and_sign(call,from).await
+// 读取多个 DAO 的所有任务，两次批量请求
+pub(crate) async fn tasks_of_daos(
+    client: &Client,
+    dao_ids: &[u64],
+    at: Option<BlockRef>,
+) -> anyhow::Result<Vec<TaskHit>, anyhow::Error> {
+    let mut batch = StorageBatch::new();
+    let projects: Vec<_> = dao_ids
+        .iter()
+        .map(|dao_id| {
+            (
+                *dao_id,
+                batch.map::<Vec<ProjectInfo<AccountId>>>(
+                    "WeteeProject",
+                    "DaoProjects",
+                    QueryKey::U64Key(*dao_id),
+                ),
+            )
+        })
+        .collect();
+    let result = batch.fetch(client, at).await?;
+
+    let mut batch = StorageBatch::new();
+    let mut items = vec![];
+    for (dao_id, item) in projects.iter() {
+        for project in result.get_or_default(item)?.iter() {
+            items.push((
+                *dao_id,
+                project.id,
+                batch.map::<Vec<TaskInfo<AccountId, Balance>>>(
+                    "WeteeProject",
+                    "Tasks",
+                    QueryKey::U64Key(project.id),
+                ),
+            ));
+        }
+    }
+    let result = batch.fetch(client, at).await?;
+
+    let mut hits = vec![];
+    for (dao_id, project_id, item) in items.iter() {
+        for task in result.get_or_default(item)?.into_iter() {
+            hits.push(TaskHit {
+                dao_id: *dao_id,
+                project_id: *project_id,
+                task,
+            });
+        }
     }
+    Ok(hits)
 }
@@ -0,0 +1,217 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::{Duration, Instant},
+};
+
+use futures::{StreamExt, TryStreamExt};
+use substrate_api_client::ac_node_api::Metadata;
+
+use crate::{
+    model::chain::{BlockRef, ChainBlock},
+    Client,
+};
+
+use super::{
+    block::{call_dao_id, is_create_dao, IndexedBlock},
+    load_dao_state, resolve_proposals, Indexer,
+};
+
+/// 补录配置
+#[derive(Debug, Clone)]
+pub struct BackfillConfig {
+    // 开始区块
+    pub from: u64,
+    // 结束区块，None 为当前最新确认的区块
+    pub to: Option<u64>,
+    // 并发获取区块的连接数
+    pub concurrency: usize,
+    // 每个事务写入的区块数
+    pub batch_size: u64,
+}
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        Self {
+            from: 0,
+            to: None,
+            concurrency: 8,
+            batch_size: 200,
+        }
+    }
+}
+
+/// 补录进度
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackfillProgress {
+    pub from: u64,
+    pub to: u64,
+    // 已完成的最新区块
+    pub current: u64,
+    // 本次运行处理的区块数
+    pub processed: u64,
+    // 本次运行的处理速度
+    pub blocks_per_second: f64,
+}
+
+impl BackfillProgress {
+    /// 完成百分比
+    pub fn percent(&self) -> f64 {
+        let total = self.to.saturating_sub(self.from) + 1;
+        let done = self.current.saturating_sub(self.from) + 1;
+        done as f64 * 100.0 / total as f64
+    }
+}
+
+impl Indexer {
+    /// 从历史区块补录索引，进度按 from 记录，中断后使用相同的 from 继续
+    pub async fn backfill<F: FnMut(&BackfillProgress)>(
+        &mut self,
+        config: BackfillConfig,
+        mut on_progress: F,
+    ) -> anyhow::Result<()> {
+        let head = self.client.get_block_number().await?;
+        let to = config.to.unwrap_or(head).min(head);
+        let from = config.from;
+        if from > to {
+            return Ok(());
+        }
+
+        let mut next = self.store.backfill_next(from).await?.unwrap_or(from);
+        let workers = start_workers(&self.client.get_url()?, config.concurrency.max(1)).await?;
+        let mut metadata: HashMap<u32, Metadata> = HashMap::new();
+        let native_version = wetee_runtime::VERSION.spec_version;
+        let started = Instant::now();
+        let mut processed = 0;
+
+        let result = async {
+            while next <= to {
+                let end = (next + config.batch_size.max(1) - 1).min(to);
+                let raws = fetch_blocks(&workers, next, end, config.concurrency.max(1)).await?;
+
+                let mut blocks = vec![];
+                let mut touched = BTreeSet::new();
+                for raw in raws.iter() {
+                    let mut block = if raw.spec_version == native_version {
+                        IndexedBlock::decode(raw)?
+                    } else {
+                        if !metadata.contains_key(&raw.spec_version) {
+                            let m = workers[0].get_metadata_at(raw.hash).await?;
+                            metadata.insert(raw.spec_version, m);
+                        }
+                        IndexedBlock::decode_with_metadata(raw, &metadata[&raw.spec_version])?
+                    };
+                    block.describe_events(self.describer(block.spec_version, block.hash).await?);
+                    resolve_proposals(&self.client, &mut block).await?;
+                    // 旧 runtime 的区块没有解码的调用，只能通过事件识别
+                    touched.extend(block.event_dao_ids());
+                    for call in block
+                        .extrinsics
+                        .iter()
+                        .filter(|x| x.success)
+                        .filter_map(|x| x.call.as_ref())
+                    {
+                        if let Some(dao_id) = call_dao_id(call) {
+                            touched.insert(dao_id);
+                        }
+                        if is_create_dao(call) {
+                            touched.insert(u64::MAX);
+                        }
+                    }
+                    blocks.push(block);
+                }
+
+                // DAO 状态只需读取批次最后一个区块的
+                let at = blocks.last().map(|b| BlockRef::Hash(b.hash));
+                if touched.remove(&u64::MAX) {
+                    let next_dao: Option<u64> = self
+                        .client
+                        .get_storage_value("WeteeOrg", "NextDaoId", at)
+                        .await?;
+                    touched.extend(5000..next_dao.unwrap_or(5000));
+                }
+                let mut states = vec![];
+                for dao_id in touched.into_iter() {
+                    if let Some(state) = load_dao_state(&self.client, dao_id, at).await? {
+                        states.push(state);
+                    }
+                }
+
+                self.store.save_blocks(&blocks, &states).await?;
+                self.store.set_backfill_next(from, to, end + 1).await?;
+
+                processed += end - next + 1;
+                next = end + 1;
+                let progress = BackfillProgress {
+                    from,
+                    to,
+                    current: end,
+                    processed,
+                    blocks_per_second: processed as f64
+                        / started.elapsed().as_secs_f64().max(0.001),
+                };
+                log::info!(
+                    "backfill {}/{} ({:.2}%, {:.1} blocks/s)",
+                    progress.current,
+                    progress.to,
+                    progress.percent(),
+                    progress.blocks_per_second
+                );
+                on_progress(&progress);
+            }
+            anyhow::Ok(())
+        }
+        .await;
+
+        for worker in workers.iter() {
+            let _ = worker.stop().await;
+        }
+        result
+    }
+}
+
+// 启动多个独立连接，用于并发获取区块
+async fn start_workers(url: &str, count: usize) -> anyhow::Result<Vec<Client>> {
+    let mut workers = vec![];
+    for _ in 0..count {
+        let client = Client::new(url.to_string())?;
+        let mut worker = Client::from_index(client.index as u32)?;
+        tokio::spawn(async move {
+            if let Err(e) = worker.start().await {
+                log::error!("backfill worker stopped: {:?}", e);
+            }
+        });
+
+        let mut waited = 0;
+        while client.get_sender().is_err() {
+            if waited > 30_000 {
+                return Err(anyhow::anyhow!("backfill worker start timeout"));
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            waited += 10;
+        }
+        workers.push(client);
+    }
+    Ok(workers)
+}
+
+// 并发获取区块，返回值按区块高度排序
+async fn fetch_blocks(
+    workers: &[Client],
+    from: u64,
+    to: u64,
+    concurrency: usize,
+) -> anyhow::Result<Vec<ChainBlock>> {
+    futures::stream::iter(from..=to)
+        .map(|number| {
+            let worker = &workers[(number % workers.len() as u64) as usize];
+            async move {
+                worker
+                    .get_block(number.try_into()?)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("block {} not found", number))
+            }
+        })
+        .buffered(concurrency)
+        .try_collect()
+        .await
+}
@@ -0,0 +1,601 @@
+use std::collections::BTreeSet;
+
+use codec::{Decode, DecodeAll, Encode};
+use frame_system::Phase;
+use sp_core::crypto::Ss58Codec;
+use sp_runtime::MultiAddress;
+use substrate_api_client::ac_node_api::{Events, Metadata, Phase as NodePhase};
+use wetee_gov::Opinion;
+use wetee_runtime::{
+    AccountId, Balance, Hash, Pledge, RuntimeCall, RuntimeEvent, UncheckedExtrinsic,
+    WeteeAssetsCall, WeteeGovCall, WeteeGuildCall, WeteeOrgCall, WeteeProjectCall,
+    WeteeSudoCall,
+};
+
+use crate::describe::{ArgValue, CallDescriber, CallNode};
+use crate::model::chain::ChainBlock;
+
+/// 区块中的事件
+pub type EventRecord = frame_system::EventRecord<RuntimeEvent, Hash>;
+
+/// 解码后的交易
+#[derive(Debug, Clone)]
+pub struct IndexedExtrinsic {
+    // 交易在区块中的序号
+    pub index: u32,
+    // 签名账户，无签名交易为 None
+    pub signer: Option<AccountId>,
+    // 调用，无法用当前 runtime 解码时为 None
+    pub call: Option<RuntimeCall>,
+    // 是否执行成功
+    pub success: bool,
+    // run_proposal 执行的提案调用，由 resolve_proposals 填写
+    pub executed: Option<RuntimeCall>,
+}
+
+/// 项目任务事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskEvent {
+    pub dao_id: u64,
+    pub project_id: u64,
+    pub task_id: u64,
+    // 事件名称
+    pub name: String,
+}
+
+impl IndexedExtrinsic {
+    /// 交易中的项目资金申请，包括直接、sudo 和提案执行的申请
+    pub fn fund(&self) -> Option<FundCall> {
+        self.call
+            .as_ref()
+            .and_then(fund_call)
+            .or_else(|| self.executed.as_ref().and_then(fund_call))
+    }
+}
+
+/// 解码后的区块
+#[derive(Debug, Clone)]
+pub struct IndexedBlock {
+    pub number: u64,
+    pub hash: Hash,
+    pub parent_hash: Hash,
+    pub timestamp: u64,
+    pub spec_version: u32,
+    pub extrinsics: Vec<IndexedExtrinsic>,
+    // 使用当前 runtime 解码的事件，runtime 不一致时为空
+    pub events: Vec<EventRecord>,
+    // 每个事件产生的交易和 SCALE 编码 (不含 phase 和 topics)，可用区块当时的元数据解析
+    pub raw_events: Vec<(Option<u32>, Vec<u8>)>,
+    // 使用元数据解析的事件，由 describe_events 填写
+    pub described: Vec<DescribedEvent>,
+}
+
+/// 使用元数据解析的事件
+#[derive(Debug, Clone)]
+pub struct DescribedEvent {
+    // 事件在区块中的序号
+    pub index: u32,
+    // 产生事件的交易，区块初始化和结束时的事件为 None
+    pub extrinsic_index: Option<u32>,
+    pub event: CallNode,
+}
+
+/// 转账
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferCall {
+    // 0 为原生代币，其他为 DAO 资产 ID
+    pub asset_id: u64,
+    pub to: AccountId,
+    pub amount: u128,
+}
+
+/// 事件中的转账，包括 sudo 和提案执行产生的转账
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferEvent {
+    // 0 为原生代币，其他为 DAO 资产 ID
+    pub asset_id: u64,
+    pub from: AccountId,
+    pub to: AccountId,
+    pub amount: u128,
+}
+
+/// 投票
+#[derive(Debug, Clone)]
+pub struct VoteCall {
+    pub dao_id: u64,
+    pub referendum_index: u32,
+    pub pledge: Pledge<Balance>,
+    pub approve: bool,
+}
+
+/// 项目资金申请
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FundCall {
+    pub dao_id: u64,
+    pub project_id: u64,
+    pub amount: u128,
+}
+
+impl IndexedBlock {
+    /// 使用 SDK 编译时的 runtime 类型解码区块中的交易和事件
+    /// 只用于 spec_version 与 SDK 一致的区块，要求完整消耗输入，避免把其他版本的数据解码成错误的类型
+    pub fn decode(block: &ChainBlock) -> anyhow::Result<Self> {
+        let events: Vec<EventRecord> = if block.events.is_empty() {
+            vec![]
+        } else {
+            DecodeAll::decode_all(&mut block.events.as_slice())?
+        };
+        let failed = events
+            .iter()
+            .filter_map(|e| match (&e.phase, &e.event) {
+                (
+                    Phase::ApplyExtrinsic(index),
+                    RuntimeEvent::System(frame_system::Event::ExtrinsicFailed { .. }),
+                ) => Some(*index),
+                _ => None,
+            })
+            .collect();
+
+        let raw_events = events
+            .iter()
+            .map(|e| match e.phase {
+                Phase::ApplyExtrinsic(index) => (Some(index), e.event.encode()),
+                _ => (None, e.event.encode()),
+            })
+            .collect();
+        let mut decoded = Self::build(block, events, raw_events, failed);
+        for (raw, xt) in block.extrinsics.iter().zip(decoded.extrinsics.iter_mut()) {
+            match UncheckedExtrinsic::decode_all(&mut raw.as_slice()) {
+                Ok(ext) => {
+                    xt.signer = ext.signature.and_then(|(address, _, _)| match address {
+                        MultiAddress::Id(id) => Some(id),
+                        _ => None,
+                    });
+                    xt.call = Some(ext.function);
+                }
+                Err(e) => {
+                    log::warn!("block {} extrinsic {} decode failed: {:?}", block.number, xt.index, e)
+                }
+            }
+        }
+        Ok(decoded)
+    }
+
+    /// 使用区块当时的元数据解码事件，用于 spec_version 与 SDK 不一致的区块
+    /// 不使用 SDK 的 runtime 类型解码，交易的调用和签名账户均为 None，只保留成功状态和原始事件
+    pub fn decode_with_metadata(block: &ChainBlock, metadata: &Metadata) -> anyhow::Result<Self> {
+        if block.spec_version == wetee_runtime::VERSION.spec_version {
+            return Self::decode(block);
+        }
+
+        let mut failed = BTreeSet::new();
+        let mut raw_events = vec![];
+        let events = Events::new(metadata.clone(), block.hash, block.events.clone());
+        for event in events.iter() {
+            let event = event.map_err(|e| anyhow::anyhow!("{:?}", e))?;
+            if event.pallet_name() == "System" && event.variant_name() == "ExtrinsicFailed" {
+                if let NodePhase::ApplyExtrinsic(index) = event.phase() {
+                    failed.insert(index);
+                }
+            }
+            let mut bytes = vec![event.pallet_index(), event.variant_index()];
+            bytes.extend_from_slice(event.field_bytes());
+            let index = match event.phase() {
+                NodePhase::ApplyExtrinsic(index) => Some(index),
+                _ => None,
+            };
+            raw_events.push((index, bytes));
+        }
+
+        log::warn!(
+            "block {} uses runtime {}, {} extrinsics are not decoded",
+            block.number,
+            block.spec_version,
+            block.extrinsics.len()
+        );
+        Ok(Self::build(block, vec![], raw_events, failed))
+    }
+
+    // 交易只填写序号和成功状态
+    fn build(
+        block: &ChainBlock,
+        events: Vec<EventRecord>,
+        raw_events: Vec<(Option<u32>, Vec<u8>)>,
+        failed: BTreeSet<u32>,
+    ) -> Self {
+        let extrinsics = (0..block.extrinsics.len() as u32)
+            .map(|index| IndexedExtrinsic {
+                index,
+                signer: None,
+                call: None,
+                success: !failed.contains(&index),
+                executed: None,
+            })
+            .collect();
+
+        IndexedBlock {
+            number: block.number.into(),
+            hash: block.hash,
+            parent_hash: block.parent_hash,
+            timestamp: block.timestamp,
+            spec_version: block.spec_version,
+            extrinsics,
+            events,
+            raw_events,
+            described: vec![],
+        }
+    }
+
+    /// 使用区块所在 runtime 的元数据解析事件，无法解析的事件只记录日志
+    pub fn describe_events(&mut self, describer: &CallDescriber) {
+        self.described = self
+            .raw_events
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (extrinsic_index, raw))| match describer.describe_event(raw) {
+                Ok(event) => Some(DescribedEvent {
+                    index: index as u32,
+                    extrinsic_index: *extrinsic_index,
+                    event,
+                }),
+                Err(e) => {
+                    log::warn!("block {} event {} decode failed: {:?}", self.number, index, e);
+                    None
+                }
+            })
+            .collect();
+    }
+
+    /// 事件所修改的 DAO，按事件中的 dao_id 字段识别
+    /// 包含治理执行、sudo 执行和区块初始化时产生的事件
+    pub fn event_dao_ids(&self) -> BTreeSet<u64> {
+        self.described
+            .iter()
+            .filter(|e| DAO_PALLETS.contains(&e.event.pallet.as_str()))
+            .filter_map(|e| e.event.number_arg("dao_id"))
+            .filter_map(|id| u64::try_from(id).ok())
+            .collect()
+    }
+
+    /// 交易产生的转账，来自转账事件，包括直接、sudo 和提案执行的转账
+    /// 返回交易序号、事件序号和转账，WeteeAsset 和 Tokens 可能为同一笔转账各产生一个事件，只保留第一个
+    pub fn transfers(&self) -> Vec<(u32, u32, TransferEvent)> {
+        let mut transfers: Vec<(u32, u32, TransferEvent)> = vec![];
+        for described in self.described.iter() {
+            let index = match described.extrinsic_index {
+                Some(index) => index,
+                None => continue,
+            };
+            let transfer = match transfer_event(&described.event) {
+                Some(transfer) => transfer,
+                None => continue,
+            };
+            if transfers.iter().any(|(i, _, t)| *i == index && *t == transfer) {
+                continue;
+            }
+            transfers.push((index, described.index, transfer));
+        }
+        transfers
+    }
+
+    /// 交易产生的事件
+    pub fn events_of(&self, index: u32) -> impl Iterator<Item = &EventRecord> {
+        self.events
+            .iter()
+            .filter(move |e| e.phase == Phase::ApplyExtrinsic(index))
+    }
+}
+
+/// 调用所修改的 DAO，sudo 调用返回内部调用的 DAO
+pub fn call_dao_id(call: &RuntimeCall) -> Option<u64> {
+    match call {
+        RuntimeCall::WeteeOrg(c) => match c {
+            WeteeOrgCall::create_roadmap_task { dao_id, .. }
+            | WeteeOrgCall::update_roadmap_task { dao_id, .. }
+            | WeteeOrgCall::org_integrate_app { dao_id, .. }
+            | WeteeOrgCall::update_org_app_status { dao_id, .. } => Some(*dao_id),
+            _ => None,
+        },
+        RuntimeCall::WeteeAsset(c) => match c {
+            WeteeAssetsCall::create_asset { dao_id, .. }
+            | WeteeAssetsCall::set_existenial_deposit { dao_id, .. }
+            | WeteeAssetsCall::set_metadata { dao_id, .. }
+            | WeteeAssetsCall::burn { dao_id, .. }
+            | WeteeAssetsCall::transfer { dao_id, .. }
+            | WeteeAssetsCall::join_request { dao_id, .. } => Some(*dao_id),
+            _ => None,
+        },
+        RuntimeCall::WeteeGuild(c) => match c {
+            WeteeGuildCall::create_guild { dao_id, .. }
+            | WeteeGuildCall::guild_join_request { dao_id, .. } => Some(*dao_id),
+            _ => None,
+        },
+        RuntimeCall::WeteeProject(c) => match c {
+            WeteeProjectCall::create_project { dao_id, .. }
+            | WeteeProjectCall::project_join_request { dao_id, .. }
+            | WeteeProjectCall::create_task { dao_id, .. }
+            | WeteeProjectCall::start_task { dao_id, .. }
+            | WeteeProjectCall::request_review { dao_id, .. }
+            | WeteeProjectCall::task_done { dao_id, .. }
+            | WeteeProjectCall::join_task { dao_id, .. }
+            | WeteeProjectCall::leave_task { dao_id, .. }
+            | WeteeProjectCall::join_task_review { dao_id, .. }
+            | WeteeProjectCall::leave_task_review { dao_id, .. }
+            | WeteeProjectCall::make_review { dao_id, .. }
+            | WeteeProjectCall::apply_project_funds { dao_id, .. } => Some(*dao_id),
+            _ => None,
+        },
+        RuntimeCall::WeteeGov(c) => match c {
+            WeteeGovCall::create_propose { dao_id, .. }
+            | WeteeGovCall::start_referendum { dao_id, .. }
+            | WeteeGovCall::vote_for_referendum { dao_id, .. }
+            | WeteeGovCall::cancel_vote { dao_id, .. }
+            | WeteeGovCall::run_proposal { dao_id, .. }
+            | WeteeGovCall::unlock { dao_id, .. } => Some(*dao_id),
+            _ => None,
+        },
+        RuntimeCall::WeteeSudo(WeteeSudoCall::sudo { dao_id, .. }) => Some(*dao_id),
+        _ => None,
+    }
+}
+
+// 事件中的 dao_id 字段来自这些模块
+const DAO_PALLETS: &[&str] = &[
+    "WeteeOrg",
+    "WeteeAsset",
+    "WeteeGuild",
+    "WeteeProject",
+    "WeteeGov",
+    "WeteeSudo",
+];
+
+/// 是否为创建 DAO 的调用
+pub fn is_create_dao(call: &RuntimeCall) -> bool {
+    matches!(call, RuntimeCall::WeteeOrg(WeteeOrgCall::create_dao { .. }))
+}
+
+/// 解析转账调用
+pub fn transfer_call(call: &RuntimeCall) -> Option<TransferCall> {
+    let (asset_id, dest, amount) = match call {
+        RuntimeCall::Balances(pallet_balances::Call::transfer { dest, value })
+        | RuntimeCall::Balances(pallet_balances::Call::transfer_keep_alive { dest, value }) => {
+            (0, dest, *value)
+        }
+        RuntimeCall::WeteeAsset(WeteeAssetsCall::transfer {
+            dao_id,
+            dest,
+            amount,
+        }) => (*dao_id, dest, *amount),
+        _ => return None,
+    };
+    match dest {
+        MultiAddress::Id(to) => Some(TransferCall {
+            asset_id,
+            to: to.clone(),
+            amount,
+        }),
+        _ => None,
+    }
+}
+
+/// 解析转账事件，原生代币为 Balances 的 Transfer
+/// DAO 代币为 WeteeAsset 或 Tokens 中带有 from/to/amount 的转账事件
+pub fn transfer_event(event: &CallNode) -> Option<TransferEvent> {
+    if !event.call.starts_with("Transfer") {
+        return None;
+    }
+    let asset_id = match event.pallet.as_str() {
+        "Balances" => 0,
+        "WeteeAsset" | "Tokens" => event
+            .number_arg("dao_id")
+            .or_else(|| event.number_arg("currency_id"))
+            .and_then(|id| u64::try_from(id).ok())?,
+        _ => return None,
+    };
+    let account = |name: &str| match event.arg(name) {
+        Some(ArgValue::Address(address)) => AccountId::from_ss58check_with_version(address)
+            .ok()
+            .map(|(account, _)| account),
+        _ => None,
+    };
+    let amount = match event.arg("amount")? {
+        ArgValue::Amount { raw, .. } | ArgValue::Number(raw) => raw.parse().ok()?,
+        _ => return None,
+    };
+    Some(TransferEvent {
+        asset_id,
+        from: account("from")?,
+        to: account("to")?,
+        amount,
+    })
+}
+
+/// 解析投票调用
+pub fn vote_call(call: &RuntimeCall) -> Option<VoteCall> {
+    match call {
+        RuntimeCall::WeteeGov(WeteeGovCall::vote_for_referendum {
+            dao_id,
+            referendum_index,
+            pledge,
+            opinion,
+        }) => Some(VoteCall {
+            dao_id: *dao_id,
+            referendum_index: *referendum_index,
+            pledge: pledge.clone(),
+            approve: matches!(opinion, Opinion::YES),
+        }),
+        _ => None,
+    }
+}
+
+/// 解析 WeteeProject 中带有任务 ID 的事件，包括 sudo 和提案执行产生的事件
+/// 字段未命名的事件无法识别
+pub fn task_event(event: &CallNode) -> Option<TaskEvent> {
+    if event.pallet != "WeteeProject" {
+        return None;
+    }
+    let id = |name: &str| event.number_arg(name).and_then(|v| u64::try_from(v).ok());
+    Some(TaskEvent {
+        dao_id: id("dao_id")?,
+        project_id: id("project_id")?,
+        task_id: id("task_id")?,
+        name: event.call.clone(),
+    })
+}
+
+/// 解析项目资金申请，包含 sudo 执行的申请
+pub fn fund_call(call: &RuntimeCall) -> Option<FundCall> {
+    match call {
+        RuntimeCall::WeteeProject(WeteeProjectCall::apply_project_funds {
+            dao_id,
+            project_id,
+            amount,
+        }) => Some(FundCall {
+            dao_id: *dao_id,
+            project_id: *project_id,
+            amount: (*amount).into(),
+        }),
+        RuntimeCall::WeteeSudo(WeteeSudoCall::sudo { call, .. }) => fund_call(call),
+        _ => None,
+    }
+}
+
+/// 解析提案执行调用，返回 DAO ID 和提案序号
+pub fn run_proposal_call(call: &RuntimeCall) -> Option<(u64, u32)> {
+    match call {
+        RuntimeCall::WeteeGov(WeteeGovCall::run_proposal { dao_id, index }) => Some((*dao_id, *index)),
+        _ => None,
+    }
+}
+
+/// 账户 hex 地址
+pub fn account_hex(account: &AccountId) -> String {
+    let bytes: &[u8] = account.as_ref();
+    "0x".to_owned() + &hex::encode(bytes)
+}
+
+/// 哈希 hex
+pub fn hash_hex(hash: &Hash) -> String {
+    "0x".to_owned() + &hex::encode(hash.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::describe::Arg;
+
+    fn xt(call: RuntimeCall, executed: Option<RuntimeCall>) -> IndexedExtrinsic {
+        IndexedExtrinsic {
+            index: 0,
+            signer: None,
+            call: Some(call),
+            success: true,
+            executed,
+        }
+    }
+
+    fn apply(amount: Balance) -> RuntimeCall {
+        RuntimeCall::WeteeProject(WeteeProjectCall::apply_project_funds {
+            dao_id: 5000,
+            project_id: 1,
+            amount,
+        })
+    }
+
+    #[test]
+    fn task_event_fields() {
+        let number = |name: &str, v: u64| Arg {
+            name: Some(name.to_owned()),
+            type_name: None,
+            value: ArgValue::Number(v.to_string()),
+        };
+        let mut node = CallNode {
+            pallet: "WeteeProject".to_owned(),
+            call: "TaskDone".to_owned(),
+            args: vec![number("dao_id", 5000), number("project_id", 1), number("task_id", 2)],
+        };
+        assert_eq!(
+            task_event(&node),
+            Some(TaskEvent {
+                dao_id: 5000,
+                project_id: 1,
+                task_id: 2,
+                name: "TaskDone".to_owned(),
+            })
+        );
+        node.args.pop();
+        assert_eq!(task_event(&node), None);
+        node.pallet = "WeteeGuild".to_owned();
+        assert_eq!(task_event(&node), None);
+    }
+
+    #[test]
+    fn fund_from_call_sudo_and_proposal() {
+        let expected = |amount| FundCall { dao_id: 5000, project_id: 1, amount };
+        assert_eq!(xt(apply(10), None).fund(), Some(expected(10)));
+
+        let sudo = RuntimeCall::WeteeSudo(WeteeSudoCall::sudo {
+            dao_id: 5000,
+            call: Box::new(apply(20)),
+        });
+        assert_eq!(xt(sudo, None).fund(), Some(expected(20)));
+
+        let run = RuntimeCall::WeteeGov(WeteeGovCall::run_proposal { dao_id: 5000, index: 3 });
+        assert_eq!(run_proposal_call(&run), Some((5000, 3)));
+        assert_eq!(xt(run.clone(), None).fund(), None);
+        assert_eq!(xt(run, Some(apply(30))).fund(), Some(expected(30)));
+    }
+
+    #[test]
+    fn transfers_from_events() {
+        let account = |n: u8| AccountId::new([n; 32]);
+        let arg = |name: &str, value: ArgValue| Arg {
+            name: Some(name.to_owned()),
+            type_name: None,
+            value,
+        };
+        let transfer = |pallet: &str, id_field: &str, amount: u128| {
+            let mut args = vec![];
+            if !id_field.is_empty() {
+                args.push(arg(id_field, ArgValue::Number("5000".to_owned())));
+            }
+            args.push(arg("from", ArgValue::Address(account(1).to_ss58check())));
+            args.push(arg("to", ArgValue::Address(account(2).to_ss58check())));
+            args.push(arg("amount", ArgValue::Number(amount.to_string())));
+            CallNode {
+                pallet: pallet.to_owned(),
+                call: "Transfer".to_owned(),
+                args,
+            }
+        };
+        let event = |index: u32, extrinsic_index: Option<u32>, event: CallNode| DescribedEvent {
+            index,
+            extrinsic_index,
+            event,
+        };
+        let block = IndexedBlock {
+            number: 10,
+            hash: Hash::repeat_byte(10),
+            parent_hash: Hash::repeat_byte(9),
+            timestamp: 0,
+            spec_version: 0,
+            extrinsics: vec![],
+            events: vec![],
+            raw_events: vec![],
+            described: vec![
+                event(0, Some(1), transfer("Balances", "", 10)),
+                // 同一笔 DAO 代币转账的两个事件
+                event(1, Some(2), transfer("WeteeAsset", "dao_id", 20)),
+                event(2, Some(2), transfer("Tokens", "currency_id", 20)),
+                event(3, None, transfer("Balances", "", 30)),
+            ],
+        };
+
+        let transfers: Vec<(u32, u32, u64, u128)> = block
+            .transfers()
+            .into_iter()
+            .map(|(index, event_index, t)| (index, event_index, t.asset_id, t.amount))
+            .collect();
+        assert_eq!(transfers, vec![(1, 0, 0, 10), (2, 1, 5000, 20)]);
+    }
+}
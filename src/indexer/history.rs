@@ -0,0 +1,797 @@
+use std::{collections::BTreeMap, sync::RwLock};
+
+use frame_support::dispatch::GetCallMetadata;
+use frame_system::Phase;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use sp_core::{sr25519, Pair};
+use sqlx::{MySql, QueryBuilder, Transaction};
+use wetee_runtime::{
+    AccountId, RuntimeCall, RuntimeEvent, WeteeAssetsCall, WeteeGuildCall, WeteeProjectCall,
+    WeteeSudoCall,
+};
+
+pub use crate::model::chain::Page;
+
+use super::{
+    block::{
+        account_hex, call_dao_id, hash_hex, transfer_call, vote_call, IndexedBlock,
+    },
+    store::IndexStore,
+};
+
+/// 历史记录类型，用于过滤
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryKind {
+    Transfer,
+    Vote,
+    Task,
+    JoinRequest,
+    Call,
+}
+
+impl HistoryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryKind::Transfer => "transfer",
+            HistoryKind::Vote => "vote",
+            HistoryKind::Task => "task",
+            HistoryKind::JoinRequest => "join_request",
+            HistoryKind::Call => "call",
+        }
+    }
+}
+
+/// 账户执行的操作
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HistoryAction {
+    // 转账，asset_id 为 0 时为原生代币
+    Transfer {
+        asset_id: u64,
+        from: AccountId,
+        to: AccountId,
+        amount: u128,
+    },
+    // 投票
+    Vote {
+        dao_id: u64,
+        referendum_index: u32,
+        pledge: String,
+        approve: bool,
+    },
+    // 任务操作，action 为调用名称，创建任务时 task_id 为 None
+    Task {
+        dao_id: u64,
+        project_id: u64,
+        task_id: Option<u64>,
+        action: String,
+    },
+    // 加入 DAO / 公会 / 项目的申请
+    JoinRequest {
+        dao_id: u64,
+        // dao / guild / project
+        scope: String,
+        scope_id: u64,
+        who: AccountId,
+    },
+    // 其他调用
+    Call { pallet: String, call: String },
+}
+
+impl HistoryAction {
+    pub fn kind(&self) -> HistoryKind {
+        match self {
+            HistoryAction::Transfer { .. } => HistoryKind::Transfer,
+            HistoryAction::Vote { .. } => HistoryKind::Vote,
+            HistoryAction::Task { .. } => HistoryKind::Task,
+            HistoryAction::JoinRequest { .. } => HistoryKind::JoinRequest,
+            HistoryAction::Call { .. } => HistoryKind::Call,
+        }
+    }
+}
+
+/// 账户历史记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    // 记录所属账户
+    pub account: AccountId,
+    pub block_number: u64,
+    pub block_hash: String,
+    pub extrinsic_index: u32,
+    // 来自事件时为事件在区块中的序号
+    pub event_index: Option<u32>,
+    // 区块时间戳 (毫秒)
+    pub timestamp: u64,
+    // 交易签名账户
+    pub signer: Option<AccountId>,
+    pub dao_id: Option<u64>,
+    pub action: HistoryAction,
+    // 交易手续费，签名账户以外的记录为 None
+    pub fee: Option<u128>,
+    pub success: bool,
+}
+
+impl HistoryEntry {
+    // 同一账户下记录的唯一键
+    fn key(&self) -> (u64, u32, u32) {
+        (
+            self.block_number,
+            self.extrinsic_index,
+            self.event_index.map(|i| i + 1).unwrap_or(0),
+        )
+    }
+}
+
+/// 历史记录过滤条件，为空时不过滤
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistoryFilter {
+    pub kinds: Vec<HistoryKind>,
+    pub dao_id: Option<u64>,
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    pub success: Option<bool>,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&entry.action.kind()))
+            && self.dao_id.map_or(true, |d| entry.dao_id == Some(d))
+            && self.from_block.map_or(true, |b| entry.block_number >= b)
+            && self.to_block.map_or(true, |b| entry.block_number <= b)
+            && self.success.map_or(true, |s| entry.success == s)
+    }
+}
+
+/// 分页结果，按区块倒序排列
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryPage {
+    pub total: u64,
+    pub entries: Vec<HistoryEntry>,
+}
+
+/// 历史记录存储
+pub trait HistoryBackend: Send + Sync {
+    /// 写入记录，重复写入同一条记录会覆盖
+    fn save<'a>(&'a self, entries: &'a [HistoryEntry]) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// 查询账户的记录
+    fn query<'a>(
+        &'a self,
+        account: &'a AccountId,
+        filter: &'a HistoryFilter,
+        page: Page,
+    ) -> BoxFuture<'a, anyhow::Result<HistoryPage>>;
+
+    /// 删除指定区块之后的记录
+    fn rollback_to(&self, number: u64) -> BoxFuture<'_, anyhow::Result<()>>;
+}
+
+/// 内存存储，用于测试和小规模使用
+#[derive(Debug, Default)]
+pub struct MemoryHistory {
+    entries: RwLock<BTreeMap<(String, (u64, u32, u32)), HistoryEntry>>,
+}
+
+impl MemoryHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl HistoryBackend for MemoryHistory {
+    fn save<'a>(&'a self, entries: &'a [HistoryEntry]) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let mut map = self.entries.write().unwrap();
+            for entry in entries.iter() {
+                map.insert((account_hex(&entry.account), entry.key()), entry.clone());
+            }
+            Ok(())
+        })
+    }
+
+    fn query<'a>(
+        &'a self,
+        account: &'a AccountId,
+        filter: &'a HistoryFilter,
+        page: Page,
+    ) -> BoxFuture<'a, anyhow::Result<HistoryPage>> {
+        Box::pin(async move {
+            let map = self.entries.read().unwrap();
+            let account = account_hex(account);
+            let list: Vec<&HistoryEntry> = map
+                .range((account.clone(), (0, 0, 0))..=(account, (u64::MAX, u32::MAX, u32::MAX)))
+                .map(|(_, e)| e)
+                .filter(|e| filter.matches(e))
+                .collect();
+            Ok(HistoryPage {
+                total: list.len() as u64,
+                entries: list
+                    .into_iter()
+                    .rev()
+                    .skip(page.offset as usize)
+                    .take(page.limit as usize)
+                    .cloned()
+                    .collect(),
+            })
+        })
+    }
+
+    fn rollback_to(&self, number: u64) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            self.entries
+                .write()
+                .unwrap()
+                .retain(|_, e| e.block_number <= number);
+            Ok(())
+        })
+    }
+}
+
+impl HistoryBackend for IndexStore {
+    fn save<'a>(&'a self, entries: &'a [HistoryEntry]) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let mut tx = self.pool.begin().await?;
+            save_history(&mut tx, entries).await?;
+            tx.commit().await?;
+            Ok(())
+        })
+    }
+
+    fn query<'a>(
+        &'a self,
+        account: &'a AccountId,
+        filter: &'a HistoryFilter,
+        page: Page,
+    ) -> BoxFuture<'a, anyhow::Result<HistoryPage>> {
+        Box::pin(async move {
+            let mut count = QueryBuilder::new("SELECT COUNT(*) FROM account_history");
+            push_history_filter(&mut count, account, filter);
+            let (total,): (i64,) = count.build_query_as().fetch_one(&self.pool).await?;
+
+            let mut select = QueryBuilder::new("SELECT data FROM account_history");
+            push_history_filter(&mut select, account, filter);
+            select
+                .push(" ORDER BY block_number DESC, extrinsic_index DESC, seq DESC LIMIT ")
+                .push_bind(page.limit)
+                .push(" OFFSET ")
+                .push_bind(page.offset);
+            let rows: Vec<(String,)> = select.build_query_as().fetch_all(&self.pool).await?;
+
+            let mut entries = vec![];
+            for (data,) in rows.into_iter() {
+                entries.push(serde_json::from_str(&data)?);
+            }
+            Ok(HistoryPage {
+                total: total as u64,
+                entries,
+            })
+        })
+    }
+
+    fn rollback_to(&self, number: u64) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            sqlx::query("DELETE FROM account_history WHERE block_number > ?")
+                .bind(number)
+                .execute(&self.pool)
+                .await?;
+            Ok(())
+        })
+    }
+}
+
+/// 账户历史查询
+pub struct History<B: HistoryBackend> {
+    pub backend: B,
+}
+
+impl<B: HistoryBackend> History<B> {
+    pub fn new(backend: B) -> Self {
+        Self { backend }
+    }
+
+    /// 将区块中的记录写入存储
+    pub async fn index_block(&self, block: &IndexedBlock) -> anyhow::Result<()> {
+        self.backend.save(&history_entries(block)).await
+    }
+
+    /// 账户的交易和事件记录，按时间倒序排列
+    pub async fn for_account(
+        &self,
+        address: String,
+        filter: HistoryFilter,
+        page: Page,
+    ) -> anyhow::Result<HistoryPage> {
+        let account: AccountId = sr25519::Public::from_string(&address)
+            .map_err(|e| anyhow::anyhow!("invalid address {}: {:?}", address, e))?
+            .into();
+        self.backend.query(&account, &filter, page).await
+    }
+}
+
+/// 从区块中提取所有账户的历史记录
+pub fn history_entries(block: &IndexedBlock) -> Vec<HistoryEntry> {
+    let mut entries = vec![];
+    let mut fees = BTreeMap::new();
+    for record in block.events.iter() {
+        if let (
+            Phase::ApplyExtrinsic(index),
+            RuntimeEvent::TransactionPayment(
+                pallet_transaction_payment::Event::TransactionFeePaid { actual_fee, .. },
+            ),
+        ) = (&record.phase, &record.event)
+        {
+            fees.insert(*index, *actual_fee);
+        }
+    }
+
+    let entry = |account: &AccountId,
+                 index: u32,
+                 event_index: Option<u32>,
+                 signer: Option<AccountId>,
+                 dao_id: Option<u64>,
+                 action: HistoryAction,
+                 fee: Option<u128>,
+                 success: bool| HistoryEntry {
+        account: account.clone(),
+        block_number: block.number,
+        block_hash: hash_hex(&block.hash),
+        extrinsic_index: index,
+        event_index,
+        timestamp: block.timestamp,
+        signer,
+        dao_id,
+        action,
+        fee,
+        success,
+    };
+
+    for xt in block.extrinsics.iter() {
+        let (call, signer) = match (&xt.call, &xt.signer) {
+            (Some(call), Some(signer)) => (call, signer),
+            _ => continue,
+        };
+        let dao_id = call_dao_id(call);
+        let action = call_action(call, signer);
+        let fee = fees.get(&xt.index).cloned();
+
+        // 转账接收方和代为申请加入的账户也记录一份
+        let other = match &action {
+            HistoryAction::Transfer { to, .. } => Some(to.clone()),
+            HistoryAction::JoinRequest { who, .. } => Some(who.clone()),
+            _ => None,
+        };
+        if let Some(other) = other.filter(|o| o != signer) {
+            entries.push(entry(
+                &other,
+                xt.index,
+                None,
+                Some(signer.clone()),
+                dao_id,
+                action.clone(),
+                None,
+                xt.success,
+            ));
+        }
+        entries.push(entry(
+            signer,
+            xt.index,
+            None,
+            Some(signer.clone()),
+            dao_id,
+            action,
+            fee,
+            xt.success,
+        ));
+    }
+
+    // 直接转账以外产生的转账，例如 sudo 或提案执行，来自 Balances 和 DAO 代币的转账事件
+    for (index, event_index, transfer) in block.transfers() {
+        let xt = block.extrinsics.iter().find(|x| x.index == index);
+        if xt.and_then(|x| x.call.as_ref()).and_then(transfer_call).is_some() {
+            continue;
+        }
+
+        let signer = xt.and_then(|x| x.signer.clone());
+        let dao_id = match transfer.asset_id {
+            0 => xt.and_then(|x| x.call.as_ref()).and_then(call_dao_id),
+            asset_id => Some(asset_id),
+        };
+        let action = HistoryAction::Transfer {
+            asset_id: transfer.asset_id,
+            from: transfer.from.clone(),
+            to: transfer.to.clone(),
+            amount: transfer.amount,
+        };
+        for account in [&transfer.from, &transfer.to] {
+            entries.push(entry(
+                account,
+                index,
+                Some(event_index),
+                signer.clone(),
+                dao_id,
+                action.clone(),
+                None,
+                true,
+            ));
+        }
+    }
+
+    entries
+}
+
+// 解析调用对应的操作，sudo 调用使用内部调用
+// sudo 执行的转账由 DAO 账户转出，按转账事件记录
+fn call_action(call: &RuntimeCall, signer: &AccountId) -> HistoryAction {
+    if let RuntimeCall::WeteeSudo(WeteeSudoCall::sudo { call: inner, .. }) = call {
+        return match call_action(inner, signer) {
+            HistoryAction::Transfer { .. } => {
+                let meta = call.get_call_metadata();
+                HistoryAction::Call {
+                    pallet: meta.pallet_name.to_owned(),
+                    call: meta.function_name.to_owned(),
+                }
+            }
+            action => action,
+        };
+    }
+    if let Some(transfer) = transfer_call(call) {
+        return HistoryAction::Transfer {
+            asset_id: transfer.asset_id,
+            from: signer.clone(),
+            to: transfer.to,
+            amount: transfer.amount,
+        };
+    }
+    if let Some(vote) = vote_call(call) {
+        return HistoryAction::Vote {
+            dao_id: vote.dao_id,
+            referendum_index: vote.referendum_index,
+            pledge: format!("{:?}", vote.pledge),
+            approve: vote.approve,
+        };
+    }
+
+    let meta = call.get_call_metadata();
+    match call {
+        RuntimeCall::WeteeAsset(WeteeAssetsCall::join_request { dao_id, .. }) => {
+            HistoryAction::JoinRequest {
+                dao_id: *dao_id,
+                scope: "dao".to_owned(),
+                scope_id: *dao_id,
+                who: signer.clone(),
+            }
+        }
+        RuntimeCall::WeteeGuild(WeteeGuildCall::guild_join_request {
+            dao_id,
+            guild_id,
+            who,
+        }) => HistoryAction::JoinRequest {
+            dao_id: *dao_id,
+            scope: "guild".to_owned(),
+            scope_id: *guild_id,
+            who: who.clone(),
+        },
+        RuntimeCall::WeteeProject(WeteeProjectCall::project_join_request {
+            dao_id,
+            project_id,
+            who,
+        }) => HistoryAction::JoinRequest {
+            dao_id: *dao_id,
+            scope: "project".to_owned(),
+            scope_id: *project_id,
+            who: who.clone(),
+        },
+        RuntimeCall::WeteeProject(WeteeProjectCall::create_task {
+            dao_id, project_id, ..
+        }) => HistoryAction::Task {
+            dao_id: *dao_id,
+            project_id: *project_id,
+            task_id: None,
+            action: meta.function_name.to_owned(),
+        },
+        RuntimeCall::WeteeProject(
+            WeteeProjectCall::start_task {
+                dao_id,
+                project_id,
+                task_id,
+            }
+            | WeteeProjectCall::request_review {
+                dao_id,
+                project_id,
+                task_id,
+            }
+            | WeteeProjectCall::task_done {
+                dao_id,
+                project_id,
+                task_id,
+            }
+            | WeteeProjectCall::join_task {
+                dao_id,
+                project_id,
+                task_id,
+            }
+            | WeteeProjectCall::leave_task {
+                dao_id,
+                project_id,
+                task_id,
+            }
+            | WeteeProjectCall::join_task_review {
+                dao_id,
+                project_id,
+                task_id,
+            }
+            | WeteeProjectCall::leave_task_review {
+                dao_id,
+                project_id,
+                task_id,
+            }
+            | WeteeProjectCall::make_review {
+                dao_id,
+                project_id,
+                task_id,
+                ..
+            },
+        ) => HistoryAction::Task {
+            dao_id: *dao_id,
+            project_id: *project_id,
+            task_id: Some(*task_id),
+            action: meta.function_name.to_owned(),
+        },
+        _ => HistoryAction::Call {
+            pallet: meta.pallet_name.to_owned(),
+            call: meta.function_name.to_owned(),
+        },
+    }
+}
+
+// 写入历史记录
+pub(crate) async fn save_history(
+    tx: &mut Transaction<'_, MySql>,
+    entries: &[HistoryEntry],
+) -> anyhow::Result<()> {
+    for entry in entries.iter() {
+        let (block_number, extrinsic_index, seq) = entry.key();
+        sqlx::query(
+            "REPLACE INTO account_history (account, block_number, extrinsic_index, seq, kind, dao_id, success, data) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(account_hex(&entry.account))
+        .bind(block_number)
+        .bind(extrinsic_index)
+        .bind(seq)
+        .bind(entry.action.kind().as_str())
+        .bind(entry.dao_id)
+        .bind(entry.success)
+        .bind(serde_json::to_string(entry)?)
+        .execute(&mut *tx)
+        .await?;
+    }
+    Ok(())
+}
+
+fn push_history_filter<'a>(
+    query: &mut QueryBuilder<'a, MySql>,
+    account: &AccountId,
+    filter: &'a HistoryFilter,
+) {
+    query.push(" WHERE account = ").push_bind(account_hex(account));
+    if !filter.kinds.is_empty() {
+        query.push(" AND kind IN (");
+        let mut kinds = query.separated(", ");
+        for kind in filter.kinds.iter() {
+            kinds.push_bind(kind.as_str());
+        }
+        query.push(")");
+    }
+    if let Some(dao_id) = filter.dao_id {
+        query.push(" AND dao_id = ").push_bind(dao_id);
+    }
+    if let Some(from) = filter.from_block {
+        query.push(" AND block_number >= ").push_bind(from);
+    }
+    if let Some(to) = filter.to_block {
+        query.push(" AND block_number <= ").push_bind(to);
+    }
+    if let Some(success) = filter.success {
+        query.push(" AND success = ").push_bind(success);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sp_core::crypto::Ss58Codec;
+    use sp_runtime::MultiAddress;
+    use wetee_runtime::{Hash, WeteeGovCall};
+
+    use super::*;
+    use crate::describe::{Arg, ArgValue, CallNode};
+    use crate::indexer::block::{DescribedEvent, IndexedExtrinsic};
+
+    const DAO_ID: u64 = 5000;
+
+    fn account(n: u8) -> AccountId {
+        AccountId::new([n; 32])
+    }
+
+    fn arg(name: &str, value: ArgValue) -> Arg {
+        Arg {
+            name: Some(name.to_owned()),
+            type_name: None,
+            value,
+        }
+    }
+
+    fn transfer_node(pallet: &str, id_field: Option<&str>, from: u8, to: u8, amount: u128) -> CallNode {
+        let mut args = vec![];
+        if let Some(field) = id_field {
+            args.push(arg(field, ArgValue::Number(DAO_ID.to_string())));
+        }
+        args.push(arg("from", ArgValue::Address(account(from).to_ss58check())));
+        args.push(arg("to", ArgValue::Address(account(to).to_ss58check())));
+        args.push(arg("amount", ArgValue::Number(amount.to_string())));
+        CallNode {
+            pallet: pallet.to_owned(),
+            call: "Transfer".to_owned(),
+            args,
+        }
+    }
+
+    fn balances_transfer(to: u8, value: u128) -> RuntimeCall {
+        RuntimeCall::Balances(pallet_balances::Call::transfer {
+            dest: MultiAddress::Id(account(to)),
+            value,
+        })
+    }
+
+    // 区块只有一笔由账户 1 签名的交易
+    fn block(call: RuntimeCall, events: Vec<CallNode>) -> IndexedBlock {
+        IndexedBlock {
+            number: 10,
+            hash: Hash::repeat_byte(10),
+            parent_hash: Hash::repeat_byte(9),
+            timestamp: 0,
+            spec_version: 0,
+            extrinsics: vec![IndexedExtrinsic {
+                index: 1,
+                signer: Some(account(1)),
+                call: Some(call),
+                success: true,
+                executed: None,
+            }],
+            events: vec![],
+            raw_events: vec![],
+            described: events
+                .into_iter()
+                .enumerate()
+                .map(|(index, event)| DescribedEvent {
+                    index: index as u32,
+                    extrinsic_index: Some(1),
+                    event,
+                })
+                .collect(),
+        }
+    }
+
+    fn transfers(entries: &[HistoryEntry]) -> Vec<(AccountId, u64, AccountId, AccountId)> {
+        entries
+            .iter()
+            .filter_map(|e| match &e.action {
+                HistoryAction::Transfer {
+                    asset_id, from, to, ..
+                } => Some((e.account.clone(), *asset_id, from.clone(), to.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn direct_transfer_recorded_once() {
+        let entries = history_entries(&block(
+            balances_transfer(2, 100),
+            vec![transfer_node("Balances", None, 1, 2, 100)],
+        ));
+        assert_eq!(
+            transfers(&entries),
+            vec![
+                (account(2), 0, account(1), account(2)),
+                (account(1), 0, account(1), account(2)),
+            ]
+        );
+        assert_eq!(entries[1].fee, None);
+        assert!(entries.iter().all(|e| e.event_index.is_none()));
+    }
+
+    #[test]
+    fn sudo_transfer_uses_event_accounts() {
+        let call = RuntimeCall::WeteeSudo(WeteeSudoCall::sudo {
+            dao_id: DAO_ID,
+            call: Box::new(balances_transfer(2, 100)),
+        });
+        // DAO 账户 3 转出
+        let entries = history_entries(&block(call, vec![transfer_node("Balances", None, 3, 2, 100)]));
+
+        assert_eq!(
+            transfers(&entries),
+            vec![
+                (account(3), 0, account(3), account(2)),
+                (account(2), 0, account(3), account(2)),
+            ]
+        );
+        let signer: Vec<_> = entries.iter().filter(|e| e.account == account(1)).collect();
+        assert_eq!(signer.len(), 1);
+        assert_eq!(
+            signer[0].action,
+            HistoryAction::Call {
+                pallet: "WeteeSudo".to_owned(),
+                call: "sudo".to_owned()
+            }
+        );
+        assert!(entries.iter().all(|e| e.dao_id == Some(DAO_ID)));
+    }
+
+    #[test]
+    fn asset_transfer_from_proposal() {
+        let call = RuntimeCall::WeteeGov(WeteeGovCall::run_proposal {
+            dao_id: DAO_ID,
+            index: 0,
+        });
+        let entries = history_entries(&block(
+            call,
+            vec![
+                transfer_node("WeteeAsset", Some("dao_id"), 3, 2, 50),
+                transfer_node("Tokens", Some("currency_id"), 3, 2, 50),
+            ],
+        ));
+        assert_eq!(
+            transfers(&entries),
+            vec![
+                (account(3), DAO_ID, account(3), account(2)),
+                (account(2), DAO_ID, account(3), account(2)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn memory_history_query() {
+        let history = History::new(MemoryHistory::new());
+        let call = RuntimeCall::WeteeSudo(WeteeSudoCall::sudo {
+            dao_id: DAO_ID,
+            call: Box::new(balances_transfer(2, 100)),
+        });
+        let sudo = block(call, vec![transfer_node("Balances", None, 3, 2, 100)]);
+        let mut direct = block(balances_transfer(2, 7), vec![]);
+        direct.number = 11;
+        history.index_block(&sudo).await.unwrap();
+        history.index_block(&direct).await.unwrap();
+        // 重复写入不产生重复记录
+        history.index_block(&direct).await.unwrap();
+
+        let all = history
+            .backend
+            .query(&account(2), &HistoryFilter::default(), Page::default())
+            .await
+            .unwrap();
+        assert_eq!(all.total, 2);
+        assert_eq!(all.entries[0].block_number, 11);
+
+        let filter = HistoryFilter {
+            kinds: vec![HistoryKind::Call],
+            ..Default::default()
+        };
+        let calls = history
+            .backend
+            .query(&account(1), &filter, Page::default())
+            .await
+            .unwrap();
+        assert_eq!(calls.total, 1);
+
+        history.backend.rollback_to(10).await.unwrap();
+        let all = history
+            .backend
+            .query(&account(2), &HistoryFilter::default(), Page::default())
+            .await
+            .unwrap();
+        assert_eq!(all.total, 1);
+    }
+}
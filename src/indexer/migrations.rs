@@ -0,0 +1,205 @@
+use sqlx::MySqlPool;
+
+/// 数据库迁移，按版本号顺序执行，每条 SQL 单独执行
+pub const MIGRATIONS: &[(i64, &str)] = &[
+    (
+        1,
+        "CREATE TABLE IF NOT EXISTS indexer_blocks (
+            number BIGINT UNSIGNED NOT NULL PRIMARY KEY,
+            hash CHAR(66) NOT NULL,
+            parent_hash CHAR(66) NOT NULL,
+            timestamp BIGINT UNSIGNED NOT NULL
+        )",
+    ),
+    (
+        2,
+        "CREATE TABLE IF NOT EXISTS daos (
+            dao_id BIGINT UNSIGNED NOT NULL PRIMARY KEY,
+            name VARCHAR(255) NOT NULL,
+            data LONGTEXT NOT NULL,
+            updated_block BIGINT UNSIGNED NOT NULL,
+            INDEX idx_daos_name (name)
+        )",
+    ),
+    (
+        3,
+        "CREATE TABLE IF NOT EXISTS members (
+            dao_id BIGINT UNSIGNED NOT NULL,
+            scope VARCHAR(16) NOT NULL,
+            scope_id BIGINT UNSIGNED NOT NULL,
+            account CHAR(66) NOT NULL,
+            point INT UNSIGNED NOT NULL DEFAULT 0,
+            PRIMARY KEY (dao_id, scope, scope_id, account),
+            INDEX idx_members_account (account)
+        )",
+    ),
+    (
+        4,
+        "CREATE TABLE IF NOT EXISTS guilds (
+            dao_id BIGINT UNSIGNED NOT NULL,
+            guild_id BIGINT UNSIGNED NOT NULL,
+            name VARCHAR(255) NOT NULL,
+            data LONGTEXT NOT NULL,
+            PRIMARY KEY (dao_id, guild_id)
+        )",
+    ),
+    (
+        5,
+        "CREATE TABLE IF NOT EXISTS projects (
+            dao_id BIGINT UNSIGNED NOT NULL,
+            project_id BIGINT UNSIGNED NOT NULL,
+            name VARCHAR(255) NOT NULL,
+            data LONGTEXT NOT NULL,
+            PRIMARY KEY (dao_id, project_id),
+            INDEX idx_projects_name (name)
+        )",
+    ),
+    (
+        6,
+        "CREATE TABLE IF NOT EXISTS tasks (
+            project_id BIGINT UNSIGNED NOT NULL,
+            task_id BIGINT UNSIGNED NOT NULL,
+            dao_id BIGINT UNSIGNED NOT NULL,
+            name VARCHAR(255) NOT NULL,
+            status VARCHAR(32) NOT NULL,
+            data LONGTEXT NOT NULL,
+            PRIMARY KEY (project_id, task_id),
+            INDEX idx_tasks_dao (dao_id)
+        )",
+    ),
+    (
+        7,
+        "CREATE TABLE IF NOT EXISTS referendums (
+            dao_id BIGINT UNSIGNED NOT NULL,
+            referendum_index INT UNSIGNED NOT NULL,
+            data LONGTEXT NOT NULL,
+            PRIMARY KEY (dao_id, referendum_index)
+        )",
+    ),
+    (
+        8,
+        "CREATE TABLE IF NOT EXISTS votes (
+            block_number BIGINT UNSIGNED NOT NULL,
+            extrinsic_index INT UNSIGNED NOT NULL,
+            dao_id BIGINT UNSIGNED NOT NULL,
+            referendum_index INT UNSIGNED NOT NULL,
+            voter CHAR(66) NOT NULL,
+            pledge VARCHAR(255) NOT NULL,
+            approve TINYINT(1) NOT NULL,
+            PRIMARY KEY (block_number, extrinsic_index),
+            INDEX idx_votes_referendum (dao_id, referendum_index),
+            INDEX idx_votes_voter (voter)
+        )",
+    ),
+    (
+        9,
+        "CREATE TABLE IF NOT EXISTS transfers (
+            block_number BIGINT UNSIGNED NOT NULL,
+            extrinsic_index INT UNSIGNED NOT NULL,
+            asset_id BIGINT UNSIGNED NOT NULL,
+            from_account CHAR(66) NOT NULL,
+            to_account CHAR(66) NOT NULL,
+            amount DECIMAL(39, 0) NOT NULL,
+            PRIMARY KEY (block_number, extrinsic_index),
+            INDEX idx_transfers_from (from_account),
+            INDEX idx_transfers_to (to_account)
+        )",
+    ),
+    (
+        10,
+        "ALTER TABLE indexer_blocks ADD COLUMN spec_version INT UNSIGNED NOT NULL DEFAULT 0",
+    ),
+    (
+        11,
+        "CREATE TABLE IF NOT EXISTS indexer_backfill (
+            from_block BIGINT UNSIGNED NOT NULL,
+            to_block BIGINT UNSIGNED NOT NULL,
+            next_block BIGINT UNSIGNED NOT NULL,
+            PRIMARY KEY (from_block, to_block)
+        )",
+    ),
+    (
+        12,
+        "CREATE TABLE IF NOT EXISTS account_history (
+            account CHAR(66) NOT NULL,
+            block_number BIGINT UNSIGNED NOT NULL,
+            extrinsic_index INT UNSIGNED NOT NULL,
+            seq INT UNSIGNED NOT NULL,
+            kind VARCHAR(32) NOT NULL,
+            dao_id BIGINT UNSIGNED NULL,
+            success TINYINT(1) NOT NULL,
+            data LONGTEXT NOT NULL,
+            PRIMARY KEY (account, block_number, extrinsic_index, seq),
+            INDEX idx_account_history_block (block_number)
+        )",
+    ),
+    (
+        13,
+        "CREATE TABLE IF NOT EXISTS project_funds (
+            block_number BIGINT UNSIGNED NOT NULL,
+            extrinsic_index INT UNSIGNED NOT NULL,
+            dao_id BIGINT UNSIGNED NOT NULL,
+            project_id BIGINT UNSIGNED NOT NULL,
+            applicant CHAR(66) NOT NULL,
+            amount DECIMAL(39, 0) NOT NULL,
+            PRIMARY KEY (block_number, extrinsic_index),
+            INDEX idx_project_funds_project (dao_id, project_id)
+        )",
+    ),
+    (
+        14,
+        "CREATE TABLE IF NOT EXISTS indexer_pending_refresh (
+            dao_id BIGINT UNSIGNED NOT NULL PRIMARY KEY
+        )",
+    ),
+    (
+        15,
+        "CREATE TABLE IF NOT EXISTS task_events (
+            block_number BIGINT UNSIGNED NOT NULL,
+            event_index INT UNSIGNED NOT NULL,
+            dao_id BIGINT UNSIGNED NOT NULL,
+            project_id BIGINT UNSIGNED NOT NULL,
+            task_id BIGINT UNSIGNED NOT NULL,
+            name VARCHAR(64) NOT NULL,
+            PRIMARY KEY (block_number, event_index),
+            INDEX idx_task_events_task (project_id, task_id)
+        )",
+    ),
+    (
+        16,
+        "ALTER TABLE transfers
+            ADD COLUMN event_index INT UNSIGNED NOT NULL DEFAULT 0 AFTER extrinsic_index,
+            DROP PRIMARY KEY,
+            ADD PRIMARY KEY (block_number, extrinsic_index, event_index)",
+    ),
+];
+
+/// 执行尚未执行的迁移
+pub async fn migrate(pool: &MySqlPool) -> anyhow::Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS indexer_migrations (
+            version BIGINT NOT NULL PRIMARY KEY,
+            applied_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    let (current,): (i64,) =
+        sqlx::query_as("SELECT COALESCE(MAX(version), 0) FROM indexer_migrations")
+            .fetch_one(pool)
+            .await?;
+
+    for (version, sql) in MIGRATIONS.iter().filter(|(v, _)| *v > current) {
+        let mut tx = pool.begin().await?;
+        sqlx::query(sql).execute(&mut tx).await?;
+        sqlx::query("INSERT INTO indexer_migrations (version) VALUES (?)")
+            .bind(*version)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+        log::info!("indexer migration {} applied", version);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,321 @@
+pub mod backfill;
+pub mod block;
+pub mod history;
+pub mod migrations;
+pub mod store;
+
+use std::{collections::HashMap, time::Duration};
+
+use substrate_api_client::ac_node_api::Metadata;
+use wetee_gov::Referendum;
+use wetee_org::{GuildInfo, OrgInfo};
+use wetee_project::{ProjectInfo, TaskInfo};
+use wetee_runtime::{AccountId, Balance, BlockNumber, Hash, RuntimeCall};
+
+use crate::{
+    batch::StorageBatch,
+    client::storage_key_suffix,
+    describe::CallDescriber,
+    model::chain::{BlockRef, QueryKey},
+    Client,
+};
+
+use self::{
+    block::{call_dao_id, hash_hex, is_create_dao, run_proposal_call, IndexedBlock},
+    store::IndexStore,
+};
+
+/// 区块中某个 DAO 的完整状态
+#[derive(Debug, Clone)]
+pub struct DaoState {
+    pub dao_id: u64,
+    pub info: OrgInfo<AccountId, BlockNumber>,
+    // 成员和声誉
+    pub members: Vec<(AccountId, u32)>,
+    // 公会和公会成员
+    pub guilds: Vec<(GuildInfo<AccountId, BlockNumber>, Vec<AccountId>)>,
+    // 项目、项目成员和任务
+    pub projects: Vec<(ProjectInfo<AccountId>, Vec<AccountId>, Vec<TaskInfo<AccountId, Balance>>)>,
+    // 投票
+    pub referendums: Vec<(u32, Referendum<BlockNumber, RuntimeCall, Balance>)>,
+}
+
+/// 索引配置
+#[derive(Debug, Clone)]
+pub struct IndexerConfig {
+    // MySQL 连接地址
+    pub database_url: String,
+    // 数据库为空时开始的区块
+    pub start_block: u64,
+    // 追上最新确认区块后的等待时间
+    pub poll_interval: Duration,
+}
+
+/// 链上数据索引，跟随最新确认的区块写入 MySQL
+pub struct Indexer {
+    pub client: Client,
+    pub store: IndexStore,
+    pub config: IndexerConfig,
+    // 按 runtime 版本缓存的事件解析器
+    describers: HashMap<u32, CallDescriber>,
+    // 按 runtime 版本缓存的元数据，用于解码与 SDK 版本不一致的区块
+    metadata: HashMap<u32, Metadata>,
+}
+
+impl Indexer {
+    pub async fn new(client: Client, config: IndexerConfig) -> anyhow::Result<Self> {
+        let store = IndexStore::connect(&config.database_url).await?;
+        Ok(Self {
+            client,
+            store,
+            config,
+            describers: HashMap::new(),
+            metadata: HashMap::new(),
+        })
+    }
+
+    /// 持续索引
+    pub async fn run(&mut self) -> anyhow::Result<()> {
+        loop {
+            let head = self.client.get_block_number().await?;
+            self.sync_to(head).await?;
+            tokio::time::sleep(self.config.poll_interval).await;
+        }
+    }
+
+    /// 索引到指定区块，从上次的检查点继续，返回已索引的最新区块
+    pub async fn sync_to(&mut self, target: u64) -> anyhow::Result<u64> {
+        loop {
+            let next = match self.store.last_block().await? {
+                Some(number) => number + 1,
+                None => self.config.start_block,
+            };
+            if next > target {
+                return Ok(next.saturating_sub(1));
+            }
+            self.index_block(next).await?;
+        }
+    }
+
+    /// 索引单个区块，父区块哈希不一致时回滚
+    pub async fn index_block(&mut self, number: u64) -> anyhow::Result<()> {
+        let raw = self
+            .client
+            .get_block(number.try_into()?)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("block {} not found", number))?;
+
+        if number > 0 {
+            if let Some(parent) = self.store.block_hash(number - 1).await? {
+                if parent != hash_hex(&raw.parent_hash) {
+                    return self.handle_reorg(number - 1).await;
+                }
+            }
+        }
+
+        let mut block = if raw.spec_version == wetee_runtime::VERSION.spec_version {
+            IndexedBlock::decode(&raw)?
+        } else {
+            IndexedBlock::decode_with_metadata(&raw, self.metadata(raw.spec_version, raw.hash).await?)?
+        };
+        block.describe_events(self.describer(block.spec_version, block.hash).await?);
+        resolve_proposals(&self.client, &mut block).await?;
+        let at = Some(BlockRef::Hash(block.hash));
+
+        // 回滚后尚未重新读取的 DAO 记录在数据库中，重启后仍会处理
+        let mut touched = self.store.pending_refresh().await?;
+        touched.extend(block.event_dao_ids());
+        for xt in block.extrinsics.iter().filter(|x| x.success) {
+            let call = match &xt.call {
+                Some(call) => call,
+                None => continue,
+            };
+            if let Some(dao_id) = call_dao_id(call) {
+                touched.insert(dao_id);
+            }
+            if is_create_dao(call) {
+                let next: Option<u64> = self
+                    .client
+                    .get_storage_value("WeteeOrg", "NextDaoId", at)
+                    .await?;
+                if let Some(next) = next {
+                    touched.insert(next - 1);
+                }
+            }
+        }
+
+        let mut states = vec![];
+        for dao_id in touched.iter() {
+            if let Some(state) = load_dao_state(&self.client, *dao_id, at).await? {
+                states.push(state);
+            }
+        }
+
+        self.store.save_block(&block, &states, &touched).await?;
+        log::debug!("indexed block {} ({} daos updated)", number, states.len());
+        Ok(())
+    }
+
+    // 区块所在 runtime 版本的事件解析器
+    pub(crate) async fn describer(
+        &mut self,
+        spec_version: u32,
+        hash: Hash,
+    ) -> anyhow::Result<&CallDescriber> {
+        if !self.describers.contains_key(&spec_version) {
+            let metadata = self.client.get_runtime_metadata(Some(hash)).await?;
+            self.describers.insert(spec_version, CallDescriber::new(metadata)?);
+        }
+        Ok(&self.describers[&spec_version])
+    }
+
+    // 区块所在 runtime 版本的元数据
+    async fn metadata(&mut self, spec_version: u32, hash: Hash) -> anyhow::Result<&Metadata> {
+        if !self.metadata.contains_key(&spec_version) {
+            let metadata = self.client.get_metadata_at(hash).await?;
+            self.metadata.insert(spec_version, metadata);
+        }
+        Ok(&self.metadata[&spec_version])
+    }
+
+    // 向前查找与链上一致的区块并回滚
+    async fn handle_reorg(&mut self, from: u64) -> anyhow::Result<()> {
+        let mut number = from;
+        loop {
+            let stored = self.store.block_hash(number).await?;
+            let chain = self.client.get_block(number.try_into()?).await?;
+            match (stored, chain) {
+                (Some(stored), Some(chain)) if stored == hash_hex(&chain.hash) => break,
+                (None, _) => break,
+                _ if number == 0 => break,
+                _ => number -= 1,
+            }
+        }
+
+        log::warn!("reorg detected at block {}, rolling back to {}", from, number);
+        self.store.rollback_to(number).await?;
+        Ok(())
+    }
+}
+
+/// 读取 DAO 在指定区块的完整状态，DAO 不存在时返回 None
+pub async fn load_dao_state(
+    client: &Client,
+    dao_id: u64,
+    at: Option<BlockRef>,
+) -> anyhow::Result<Option<DaoState>> {
+    let mut batch = StorageBatch::new();
+    let info = batch.map("WeteeOrg", "Daos", QueryKey::U64Key(dao_id));
+    let members = batch.map("WeteeOrg", "Members", QueryKey::U64Key(dao_id));
+    let guilds = batch.map("WeteeOrg", "Guilds", QueryKey::U64Key(dao_id));
+    let projects = batch.map("WeteeProject", "DaoProjects", QueryKey::U64Key(dao_id));
+    let result = batch.fetch(client, at).await?;
+
+    let info: OrgInfo<AccountId, BlockNumber> = match result.get(&info)? {
+        Some(info) => info,
+        None => return Ok(None),
+    };
+    let members: Vec<AccountId> = result.get_or_default(&members)?;
+    let guilds: Vec<GuildInfo<AccountId, BlockNumber>> = result.get_or_default(&guilds)?;
+    let projects: Vec<ProjectInfo<AccountId>> = result.get_or_default(&projects)?;
+
+    // 第二轮读取依赖第一轮结果的存储项
+    let mut batch = StorageBatch::new();
+    let points: Vec<_> = members
+        .iter()
+        .map(|m| {
+            batch.double_map::<u32>(
+                "WeteeOrg",
+                "MemberPoint",
+                QueryKey::U64Key(dao_id),
+                QueryKey::AccountId(m.clone()),
+            )
+        })
+        .collect();
+    let guild_members: Vec<_> = guilds
+        .iter()
+        .map(|g| {
+            batch.double_map::<Vec<AccountId>>(
+                "WeteeOrg",
+                "GuildMembers",
+                QueryKey::U64Key(dao_id),
+                QueryKey::U64Key(g.id),
+            )
+        })
+        .collect();
+    let project_items: Vec<_> = projects
+        .iter()
+        .map(|p| {
+            (
+                batch.double_map::<Vec<AccountId>>(
+                    "WeteeOrg",
+                    "ProjectMembers",
+                    QueryKey::U64Key(dao_id),
+                    QueryKey::U64Key(p.id),
+                ),
+                batch.map::<Vec<TaskInfo<AccountId, Balance>>>(
+                    "WeteeProject",
+                    "Tasks",
+                    QueryKey::U64Key(p.id),
+                ),
+            )
+        })
+        .collect();
+    let result = batch.fetch(client, at).await?;
+
+    let mut member_points = vec![];
+    for (member, item) in members.into_iter().zip(points.iter()) {
+        member_points.push((member, result.get_or_default(item)?));
+    }
+    let mut guild_list = vec![];
+    for (guild, item) in guilds.into_iter().zip(guild_members.iter()) {
+        guild_list.push((guild, result.get_or_default(item)?));
+    }
+    let mut project_list = vec![];
+    for (project, (members, tasks)) in projects.into_iter().zip(project_items.iter()) {
+        project_list.push((
+            project,
+            result.get_or_default(members)?,
+            result.get_or_default(tasks)?,
+        ));
+    }
+
+    let referendums: Vec<(String, Referendum<BlockNumber, RuntimeCall, Balance>)> = client
+        .get_storage_double_map_first("WeteeGov", "ReferendumInfoOf", QueryKey::U64Key(dao_id), at)
+        .await?;
+    let mut referendum_list = vec![];
+    for (key, referendum) in referendums.into_iter() {
+        referendum_list.push((storage_key_suffix::<u32>(&key, 4)?, referendum));
+    }
+
+    Ok(Some(DaoState {
+        dao_id,
+        info,
+        members: member_points,
+        guilds: guild_list,
+        projects: project_list,
+        referendums: referendum_list,
+    }))
+}
+
+/// 读取成功的 run_proposal 所执行的提案调用，提案在父区块中仍保存在 ReferendumInfoOf
+pub(crate) async fn resolve_proposals(client: &Client, block: &mut IndexedBlock) -> anyhow::Result<()> {
+    let at = Some(BlockRef::Hash(block.parent_hash));
+    for xt in block.extrinsics.iter_mut().filter(|x| x.success) {
+        let (dao_id, index) = match xt.call.as_ref().and_then(run_proposal_call) {
+            Some(target) => target,
+            None => continue,
+        };
+        let referendum: Option<Referendum<BlockNumber, RuntimeCall, Balance>> = client
+            .get_storage_double_map(
+                "WeteeGov",
+                "ReferendumInfoOf",
+                QueryKey::U64Key(dao_id),
+                QueryKey::U32Key(index),
+                at,
+            )
+            .await?;
+        xt.executed = referendum.map(|r| r.proposal);
+    }
+    Ok(())
+}
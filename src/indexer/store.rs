@@ -0,0 +1,657 @@
+use std::collections::BTreeSet;
+
+use codec::{Decode, Encode};
+use sqlx::{mysql::MySqlPoolOptions, FromRow, MySql, MySqlPool, Transaction};
+use wetee_org::GuildInfo;
+use wetee_project::TaskInfo;
+use wetee_runtime::{AccountId, Balance, BlockNumber};
+
+use super::{
+    block::{account_hex, hash_hex, task_event, vote_call, IndexedBlock},
+    history::{history_entries, save_history},
+    migrations::migrate,
+    DaoState,
+};
+
+// 以 dao_id 保存 DAO 状态的表，不含 daos
+const DAO_TABLES: [&str; 5] = ["members", "guilds", "projects", "tasks", "referendums"];
+
+/// 索引中的 DAO
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct IndexedDao {
+    pub dao_id: u64,
+    pub name: String,
+    pub updated_block: u64,
+}
+
+/// 索引中的成员
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct IndexedMember {
+    pub dao_id: u64,
+    // dao / guild / project
+    pub scope: String,
+    pub scope_id: u64,
+    pub account: String,
+    pub point: u32,
+}
+
+/// 索引中的任务
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct IndexedTask {
+    pub project_id: u64,
+    pub task_id: u64,
+    pub dao_id: u64,
+    pub name: String,
+    pub status: String,
+}
+
+/// 索引中的任务事件
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct IndexedTaskEvent {
+    pub block_number: u64,
+    pub event_index: u32,
+    pub dao_id: u64,
+    pub project_id: u64,
+    pub task_id: u64,
+    pub name: String,
+}
+
+/// 索引中的转账
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct IndexedTransfer {
+    pub block_number: u64,
+    pub extrinsic_index: u32,
+    pub event_index: u32,
+    pub asset_id: u64,
+    pub from_account: String,
+    pub to_account: String,
+    pub amount: String,
+}
+
+/// 索引中的项目资金申请
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct IndexedFunding {
+    pub block_number: u64,
+    pub extrinsic_index: u32,
+    pub dao_id: u64,
+    pub project_id: u64,
+    pub applicant: String,
+    pub amount: String,
+}
+
+/// MySQL 索引存储
+#[derive(Debug, Clone)]
+pub struct IndexStore {
+    pub pool: MySqlPool,
+}
+
+impl IndexStore {
+    /// 连接数据库并执行迁移
+    pub async fn connect(database_url: &str) -> anyhow::Result<Self> {
+        let pool = MySqlPoolOptions::new()
+            .max_connections(5)
+            .connect(database_url)
+            .await?;
+        migrate(&pool).await?;
+        Ok(Self { pool })
+    }
+
+    /// 已索引的最新区块
+    pub async fn last_block(&self) -> anyhow::Result<Option<u64>> {
+        let row: Option<(u64,)> =
+            sqlx::query_as("SELECT number FROM indexer_blocks ORDER BY number DESC LIMIT 1")
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row.map(|(n,)| n))
+    }
+
+    /// 已索引区块的哈希
+    pub async fn block_hash(&self, number: u64) -> anyhow::Result<Option<String>> {
+        let row: Option<(String,)> = sqlx::query_as("SELECT hash FROM indexer_blocks WHERE number = ?")
+            .bind(number)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|(h,)| h))
+    }
+
+    /// 回滚到指定区块 (保留该区块)，回滚后更新过的 DAO 标记为需要重新读取状态
+    pub async fn rollback_to(&self, number: u64) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT IGNORE INTO indexer_pending_refresh (dao_id) SELECT dao_id FROM daos WHERE updated_block > ?",
+        )
+        .bind(number)
+        .execute(&mut tx)
+        .await?;
+        // 允许重新写入回滚后的状态
+        sqlx::query("UPDATE daos SET updated_block = ? WHERE updated_block > ?")
+            .bind(number)
+            .bind(number)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query("DELETE FROM indexer_blocks WHERE number > ?")
+            .bind(number)
+            .execute(&mut tx)
+            .await?;
+        for table in ["votes", "transfers", "account_history", "project_funds", "task_events"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE block_number > ?", table))
+                .bind(number)
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        log::warn!("indexer rolled back to block {}", number);
+        Ok(())
+    }
+
+    /// 回滚后需要重新读取状态的 DAO
+    pub async fn pending_refresh(&self) -> anyhow::Result<BTreeSet<u64>> {
+        let rows: Vec<(u64,)> = sqlx::query_as("SELECT dao_id FROM indexer_pending_refresh")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
+    /// 在一个事务中写入区块、转账、投票、账户历史和 DAO 状态
+    /// refreshed 为本次已重新读取的 DAO，不在 states 中的 DAO 链上已不存在，删除其所有数据
+    pub async fn save_block(
+        &self,
+        block: &IndexedBlock,
+        states: &[DaoState],
+        refreshed: &BTreeSet<u64>,
+    ) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        save_block_data(&mut tx, block).await?;
+        for state in states.iter() {
+            save_dao_state(&mut tx, block.number, state).await?;
+        }
+        for dao_id in refreshed.iter() {
+            if !states.iter().any(|s| s.dao_id == *dao_id) {
+                remove_dao_state(&mut tx, block.number, *dao_id).await?;
+            }
+            sqlx::query("DELETE FROM indexer_pending_refresh WHERE dao_id = ?")
+                .bind(*dao_id)
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// 在一个事务中写入多个区块，DAO 状态以最后一个区块为准
+    /// 所有写入均可重复执行
+    pub async fn save_blocks(&self, blocks: &[IndexedBlock], states: &[DaoState]) -> anyhow::Result<()> {
+        let last = match blocks.last() {
+            Some(block) => block.number,
+            None => return Ok(()),
+        };
+        let mut tx = self.pool.begin().await?;
+
+        for block in blocks.iter() {
+            save_block_data(&mut tx, block).await?;
+        }
+        for state in states.iter() {
+            save_dao_state(&mut tx, last, state).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// 补录进度，返回下一个要处理的区块
+    /// 只按 from 查找，结束区块为最新区块时每次运行的 to 不同
+    pub async fn backfill_next(&self, from: u64) -> anyhow::Result<Option<u64>> {
+        let row: (Option<u64>,) =
+            sqlx::query_as("SELECT MAX(next_block) FROM indexer_backfill WHERE from_block = ?")
+                .bind(from)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(row.0)
+    }
+
+    /// 记录补录进度
+    pub async fn set_backfill_next(&self, from: u64, to: u64, next: u64) -> anyhow::Result<()> {
+        sqlx::query(
+            "REPLACE INTO indexer_backfill (from_block, to_block, next_block) VALUES (?, ?, ?)",
+        )
+        .bind(from)
+        .bind(to)
+        .bind(next)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// DAO 列表
+    pub async fn list_daos(&self, offset: u64, limit: u64) -> anyhow::Result<Vec<IndexedDao>> {
+        Ok(sqlx::query_as(
+            "SELECT dao_id, name, updated_block FROM daos ORDER BY dao_id LIMIT ? OFFSET ?",
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// 按名称搜索 DAO
+    pub async fn search_daos(&self, keyword: &str, limit: u64) -> anyhow::Result<Vec<IndexedDao>> {
+        Ok(sqlx::query_as(
+            "SELECT dao_id, name, updated_block FROM daos WHERE name LIKE ? ORDER BY dao_id LIMIT ?",
+        )
+        .bind(format!("%{}%", keyword))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// DAO 成员，含公会和项目成员
+    pub async fn members(&self, dao_id: u64) -> anyhow::Result<Vec<IndexedMember>> {
+        Ok(sqlx::query_as(
+            "SELECT dao_id, scope, scope_id, account, point FROM members WHERE dao_id = ? ORDER BY scope, scope_id, point DESC",
+        )
+        .bind(dao_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// 按 ID 查找公会，不需要读取链上的整个 Guilds 列表
+    pub async fn guild(
+        &self,
+        dao_id: u64,
+        guild_id: u64,
+    ) -> anyhow::Result<Option<GuildInfo<AccountId, BlockNumber>>> {
+        let row: Option<(String,)> =
+            sqlx::query_as("SELECT data FROM guilds WHERE dao_id = ? AND guild_id = ?")
+                .bind(dao_id)
+                .bind(guild_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        match row {
+            Some((data,)) => Ok(Some(Decode::decode(&mut hex::decode(data)?.as_slice())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 按 ID 查找任务，不需要读取链上项目的整个任务列表
+    pub async fn task(
+        &self,
+        project_id: u64,
+        task_id: u64,
+    ) -> anyhow::Result<Option<TaskInfo<AccountId, Balance>>> {
+        let row: Option<(String,)> =
+            sqlx::query_as("SELECT data FROM tasks WHERE project_id = ? AND task_id = ?")
+                .bind(project_id)
+                .bind(task_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        match row {
+            Some((data,)) => Ok(Some(Decode::decode(&mut hex::decode(data)?.as_slice())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// DAO 中的任务完成事件，不区分交易签名账户，包括 sudo 和提案执行
+    pub async fn task_done_events(&self, dao_id: u64) -> anyhow::Result<Vec<IndexedTaskEvent>> {
+        Ok(sqlx::query_as(
+            "SELECT block_number, event_index, dao_id, project_id, task_id, name FROM task_events WHERE dao_id = ? AND name = 'TaskDone' ORDER BY block_number, event_index",
+        )
+        .bind(dao_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// 账户加入的 DAO、公会和项目
+    pub async fn memberships(&self, account: &str) -> anyhow::Result<Vec<IndexedMember>> {
+        Ok(sqlx::query_as(
+            "SELECT dao_id, scope, scope_id, account, point FROM members WHERE account = ? ORDER BY dao_id",
+        )
+        .bind(account)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// DAO 下的任务
+    pub async fn tasks(&self, dao_id: u64) -> anyhow::Result<Vec<IndexedTask>> {
+        Ok(sqlx::query_as(
+            "SELECT project_id, task_id, dao_id, name, status FROM tasks WHERE dao_id = ? ORDER BY project_id, task_id",
+        )
+        .bind(dao_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// 账户相关的转账，包括 sudo 和提案执行的转账
+    pub async fn transfers_of(
+        &self,
+        account: &str,
+        offset: u64,
+        limit: u64,
+    ) -> anyhow::Result<Vec<IndexedTransfer>> {
+        Ok(sqlx::query_as(
+            "SELECT block_number, extrinsic_index, event_index, asset_id, from_account, to_account, CAST(amount AS CHAR) AS amount FROM transfers WHERE from_account = ? OR to_account = ? ORDER BY block_number DESC, extrinsic_index DESC, event_index DESC LIMIT ? OFFSET ?",
+        )
+        .bind(account)
+        .bind(account)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// 项目的资金申请
+    pub async fn project_funds(
+        &self,
+        dao_id: u64,
+        project_id: u64,
+    ) -> anyhow::Result<Vec<IndexedFunding>> {
+        Ok(sqlx::query_as(
+            "SELECT block_number, extrinsic_index, dao_id, project_id, applicant, CAST(amount AS CHAR) AS amount FROM project_funds WHERE dao_id = ? AND project_id = ? ORDER BY block_number, extrinsic_index",
+        )
+        .bind(dao_id)
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+}
+
+// 写入区块及其中的转账、投票和资金申请
+async fn save_block_data(
+    tx: &mut Transaction<'_, MySql>,
+    block: &IndexedBlock,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "REPLACE INTO indexer_blocks (number, hash, parent_hash, timestamp, spec_version) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(block.number)
+    .bind(hash_hex(&block.hash))
+    .bind(hash_hex(&block.parent_hash))
+    .bind(block.timestamp)
+    .bind(block.spec_version)
+    .execute(&mut *tx)
+    .await?;
+
+    for xt in block.extrinsics.iter().filter(|x| x.success) {
+        let (call, signer) = match (&xt.call, &xt.signer) {
+            (Some(call), Some(signer)) => (call, signer),
+            _ => continue,
+        };
+        // 提案执行的申请记录执行人
+        if let Some(fund) = xt.fund() {
+            sqlx::query(
+                "REPLACE INTO project_funds (block_number, extrinsic_index, dao_id, project_id, applicant, amount) VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(block.number)
+            .bind(xt.index)
+            .bind(fund.dao_id)
+            .bind(fund.project_id)
+            .bind(account_hex(signer))
+            .bind(fund.amount.to_string())
+            .execute(&mut *tx)
+            .await?;
+        }
+        if let Some(vote) = vote_call(call) {
+            sqlx::query(
+                "REPLACE INTO votes (block_number, extrinsic_index, dao_id, referendum_index, voter, pledge, approve) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(block.number)
+            .bind(xt.index)
+            .bind(vote.dao_id)
+            .bind(vote.referendum_index)
+            .bind(account_hex(signer))
+            .bind(format!("{:?}", vote.pledge))
+            .bind(vote.approve)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    // 转账来自转账事件，与账户历史一致，包括 sudo 和提案执行的转账
+    // 先删除区块中已有的转账，重新索引时不会留下旧的记录
+    sqlx::query("DELETE FROM transfers WHERE block_number = ?")
+        .bind(block.number)
+        .execute(&mut *tx)
+        .await?;
+    for (index, event_index, transfer) in block.transfers() {
+        sqlx::query(
+            "INSERT INTO transfers (block_number, extrinsic_index, event_index, asset_id, from_account, to_account, amount) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(block.number)
+        .bind(index)
+        .bind(event_index)
+        .bind(transfer.asset_id)
+        .bind(account_hex(&transfer.from))
+        .bind(account_hex(&transfer.to))
+        .bind(transfer.amount.to_string())
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for described in block.described.iter() {
+        if let Some(event) = task_event(&described.event) {
+            sqlx::query(
+                "REPLACE INTO task_events (block_number, event_index, dao_id, project_id, task_id, name) VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(block.number)
+            .bind(described.index)
+            .bind(event.dao_id)
+            .bind(event.project_id)
+            .bind(event.task_id)
+            .bind(event.name)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    save_history(tx, &history_entries(block)).await?;
+    Ok(())
+}
+
+// 用最新读取的状态替换 DAO 的所有数据
+// 已保存的状态不早于 block_number 时跳过，避免补录的历史状态覆盖实时索引的状态
+async fn save_dao_state(
+    tx: &mut Transaction<'_, MySql>,
+    block_number: u64,
+    state: &DaoState,
+) -> anyhow::Result<()> {
+    let dao_id = state.dao_id;
+    if indexed_after(tx, dao_id, block_number).await? {
+        return Ok(());
+    }
+    for table in DAO_TABLES {
+        sqlx::query(&format!("DELETE FROM {} WHERE dao_id = ?", table))
+            .bind(dao_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    sqlx::query("REPLACE INTO daos (dao_id, name, data, updated_block) VALUES (?, ?, ?, ?)")
+        .bind(dao_id)
+        .bind(String::from_utf8_lossy(&state.info.name).to_string())
+        .bind(hex::encode(state.info.encode()))
+        .bind(block_number)
+        .execute(&mut *tx)
+        .await?;
+
+    for (account, point) in state.members.iter() {
+        insert_member(tx, dao_id, "dao", dao_id, &account_hex(account), *point).await?;
+    }
+
+    for (guild, members) in state.guilds.iter() {
+        sqlx::query("INSERT INTO guilds (dao_id, guild_id, name, data) VALUES (?, ?, ?, ?)")
+            .bind(dao_id)
+            .bind(guild.id)
+            .bind(String::from_utf8_lossy(&guild.name).to_string())
+            .bind(hex::encode(guild.encode()))
+            .execute(&mut *tx)
+            .await?;
+        for account in members.iter() {
+            insert_member(tx, dao_id, "guild", guild.id, &account_hex(account), 0).await?;
+        }
+    }
+
+    for (project, members, tasks) in state.projects.iter() {
+        sqlx::query("INSERT INTO projects (dao_id, project_id, name, data) VALUES (?, ?, ?, ?)")
+            .bind(dao_id)
+            .bind(project.id)
+            .bind(String::from_utf8_lossy(&project.name).to_string())
+            .bind(hex::encode(project.encode()))
+            .execute(&mut *tx)
+            .await?;
+        for account in members.iter() {
+            insert_member(tx, dao_id, "project", project.id, &account_hex(account), 0).await?;
+        }
+        for task in tasks.iter() {
+            sqlx::query(
+                "INSERT INTO tasks (project_id, task_id, dao_id, name, status, data) VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(project.id)
+            .bind(task.id)
+            .bind(dao_id)
+            .bind(String::from_utf8_lossy(&task.name).to_string())
+            .bind(format!("{:?}", task.status))
+            .bind(hex::encode(task.encode()))
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    for (index, referendum) in state.referendums.iter() {
+        sqlx::query("INSERT INTO referendums (dao_id, referendum_index, data) VALUES (?, ?, ?)")
+            .bind(dao_id)
+            .bind(*index)
+            .bind(hex::encode(referendum.encode()))
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    Ok(())
+}
+
+// 删除链上已不存在的 DAO 的所有数据，例如回滚了创建 DAO 的区块
+// 已保存的状态不早于 block_number 时跳过
+async fn remove_dao_state(
+    tx: &mut Transaction<'_, MySql>,
+    block_number: u64,
+    dao_id: u64,
+) -> anyhow::Result<()> {
+    if indexed_after(tx, dao_id, block_number).await? {
+        return Ok(());
+    }
+    for table in DAO_TABLES.iter().chain(["daos"].iter()) {
+        sqlx::query(&format!("DELETE FROM {} WHERE dao_id = ?", table))
+            .bind(dao_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    log::debug!("dao {} no longer exists at block {}, removed", dao_id, block_number);
+    Ok(())
+}
+
+// DAO 已保存的状态是否不早于 block_number，并锁定该行
+async fn indexed_after(
+    tx: &mut Transaction<'_, MySql>,
+    dao_id: u64,
+    block_number: u64,
+) -> anyhow::Result<bool> {
+    let stored: Option<(u64,)> =
+        sqlx::query_as("SELECT updated_block FROM daos WHERE dao_id = ? FOR UPDATE")
+            .bind(dao_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+    match stored {
+        Some((updated,)) if updated >= block_number => {
+            log::debug!("dao {} already indexed at block {}", dao_id, updated);
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+async fn insert_member(
+    tx: &mut Transaction<'_, MySql>,
+    dao_id: u64,
+    scope: &str,
+    scope_id: u64,
+    account: &str,
+    point: u32,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "REPLACE INTO members (dao_id, scope, scope_id, account, point) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(dao_id)
+    .bind(scope)
+    .bind(scope_id)
+    .bind(account)
+    .bind(point)
+    .execute(&mut *tx)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use wetee_runtime::Hash;
+
+    use super::*;
+
+    const DAO_ID: u64 = 5000;
+
+    // 需要 MySQL，未设置 INDEXER_TEST_DATABASE_URL 时跳过
+    async fn store() -> Option<IndexStore> {
+        let url = std::env::var("INDEXER_TEST_DATABASE_URL").ok()?;
+        Some(IndexStore::connect(&url).await.unwrap())
+    }
+
+    fn block(number: u64) -> IndexedBlock {
+        IndexedBlock {
+            number,
+            hash: Hash::repeat_byte(number as u8),
+            parent_hash: Hash::repeat_byte(number as u8 - 1),
+            timestamp: 0,
+            spec_version: 0,
+            extrinsics: vec![],
+            events: vec![],
+            raw_events: vec![],
+            described: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn rollback_of_create_dao_removes_dao() {
+        let store = match store().await {
+            Some(store) => store,
+            None => return,
+        };
+
+        // 区块 10 创建了 DAO
+        sqlx::query("REPLACE INTO daos (dao_id, name, data, updated_block) VALUES (?, 'dao', '', 10)")
+            .bind(DAO_ID)
+            .execute(&store.pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "REPLACE INTO members (dao_id, scope, scope_id, account, point) VALUES (?, 'dao', ?, 'creator', 0)",
+        )
+        .bind(DAO_ID)
+        .bind(DAO_ID)
+        .execute(&store.pool)
+        .await
+        .unwrap();
+
+        // 回滚区块 10，新的区块 10 中没有创建 DAO，链上读取不到 DAO 状态
+        store.rollback_to(9).await.unwrap();
+        let pending = store.pending_refresh().await.unwrap();
+        assert!(pending.contains(&DAO_ID));
+        store.save_block(&block(10), &[], &pending).await.unwrap();
+
+        assert!(!store.pending_refresh().await.unwrap().contains(&DAO_ID));
+        assert!(store.members(DAO_ID).await.unwrap().is_empty());
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM daos WHERE dao_id = ?")
+            .bind(DAO_ID)
+            .fetch_one(&store.pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+}
@@ -0,0 +1,600 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use wetee_gov::ReferendumStatus;
+
+use crate::hander::wetee_gov::WeteeGov;
+use crate::model::amount::Amount;
+use crate::model::gov::{GovPeriod, Proposal, ReferendumView};
+
+/// 治理守护需要的链上读写，测试时可以替换为模拟实现
+pub trait GovChain: Send + Sync {
+    fn block_number(&self) -> BoxFuture<'_, anyhow::Result<u64>>;
+
+    fn periods(&self, dao_id: u64) -> BoxFuture<'_, anyhow::Result<Vec<GovPeriod>>>;
+
+    fn proposals(&self, dao_id: u64) -> BoxFuture<'_, anyhow::Result<Vec<Proposal>>>;
+
+    fn referendums(&self, dao_id: u64) -> BoxFuture<'_, anyhow::Result<Vec<ReferendumView>>>;
+
+    fn start_referendum<'a>(
+        &'a self,
+        signer: &'a str,
+        dao_id: u64,
+        propose_id: u32,
+        deposit: u128,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    fn run_proposal<'a>(
+        &'a self,
+        signer: &'a str,
+        dao_id: u64,
+        index: u32,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+impl GovChain for WeteeGov {
+    fn block_number(&self) -> BoxFuture<'_, anyhow::Result<u64>> {
+        Box::pin(self.base.get_block_number())
+    }
+
+    fn periods(&self, dao_id: u64) -> BoxFuture<'_, anyhow::Result<Vec<GovPeriod>>> {
+        Box::pin(WeteeGov::periods(self, dao_id, None))
+    }
+
+    fn proposals(&self, dao_id: u64) -> BoxFuture<'_, anyhow::Result<Vec<Proposal>>> {
+        Box::pin(WeteeGov::proposals(self, dao_id, None))
+    }
+
+    fn referendums(&self, dao_id: u64) -> BoxFuture<'_, anyhow::Result<Vec<ReferendumView>>> {
+        Box::pin(WeteeGov::referendums(self, dao_id, None))
+    }
+
+    fn start_referendum<'a>(
+        &'a self,
+        signer: &'a str,
+        dao_id: u64,
+        propose_id: u32,
+        deposit: u128,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(WeteeGov::start_referendum(
+            self,
+            signer.to_string(),
+            dao_id,
+            propose_id,
+            Amount::native(deposit),
+        ))
+    }
+
+    fn run_proposal<'a>(
+        &'a self,
+        signer: &'a str,
+        dao_id: u64,
+        index: u32,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(WeteeGov::run_proposal(self, signer.to_string(), dao_id, index))
+    }
+}
+
+/// 守护执行的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeeperActionKind {
+    // 为提案开始投票
+    Start,
+    // 执行通过的提案
+    Run,
+    // 投票未通过，只记录
+    Rejected,
+}
+
+/// 操作记录的键
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeeperKey {
+    pub dao_id: u64,
+    pub kind: KeeperActionKind,
+    // 提案或投票序号
+    pub index: u32,
+}
+
+impl KeeperKey {
+    fn as_string(&self) -> String {
+        format!("{:?}:{}:{}", self.kind, self.dao_id, self.index)
+    }
+}
+
+/// 守护已经提交过的操作，重启后避免重复提交
+pub trait KeeperState: Send + Sync {
+    /// 上次提交操作时的区块
+    fn last_attempt(&self, key: &KeeperKey) -> Option<u64>;
+
+    fn record(&self, key: &KeeperKey, block_number: u64) -> anyhow::Result<()>;
+}
+
+/// 内存记录，重启后丢失
+#[derive(Debug, Default)]
+pub struct MemoryKeeperState {
+    attempts: RwLock<BTreeMap<String, u64>>,
+}
+
+impl KeeperState for MemoryKeeperState {
+    fn last_attempt(&self, key: &KeeperKey) -> Option<u64> {
+        self.attempts.read().unwrap().get(&key.as_string()).copied()
+    }
+
+    fn record(&self, key: &KeeperKey, block_number: u64) -> anyhow::Result<()> {
+        self.attempts.write().unwrap().insert(key.as_string(), block_number);
+        Ok(())
+    }
+}
+
+/// JSON 文件记录
+#[derive(Debug)]
+pub struct FileKeeperState {
+    path: PathBuf,
+    attempts: RwLock<BTreeMap<String, u64>>,
+}
+
+impl FileKeeperState {
+    pub fn open(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let attempts = if path.exists() {
+            serde_json::from_slice(&std::fs::read(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            path,
+            attempts: RwLock::new(attempts),
+        })
+    }
+}
+
+impl KeeperState for FileKeeperState {
+    fn last_attempt(&self, key: &KeeperKey) -> Option<u64> {
+        self.attempts.read().unwrap().get(&key.as_string()).copied()
+    }
+
+    fn record(&self, key: &KeeperKey, block_number: u64) -> anyhow::Result<()> {
+        let mut attempts = self.attempts.write().unwrap();
+        attempts.insert(key.as_string(), block_number);
+        // 先写临时文件再替换，避免写入中断损坏记录
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&*attempts)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// 守护配置
+#[derive(Debug, Clone)]
+pub struct KeeperConfig {
+    pub dao_ids: Vec<u64>,
+    // 签名账户，需要已在 KERINGS 中
+    pub signer: String,
+    // 检查间隔
+    pub interval: Duration,
+    // 操作提交后未生效时，间隔多少区块重试
+    pub retry_after: u64,
+    pub auto_start: bool,
+    pub auto_run: bool,
+}
+
+impl Default for KeeperConfig {
+    fn default() -> Self {
+        Self {
+            dao_ids: vec![],
+            signer: String::new(),
+            interval: Duration::from_secs(12),
+            retry_after: 10,
+            auto_start: true,
+            auto_run: true,
+        }
+    }
+}
+
+/// 操作结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeeperOutcome {
+    pub dao_id: u64,
+    pub kind: KeeperActionKind,
+    pub index: u32,
+    pub block_number: u64,
+    pub error: Option<String>,
+}
+
+/// 治理守护，自动开始投票并在执行延迟后执行通过的提案
+pub struct GovKeeper<C: GovChain, S: KeeperState> {
+    pub chain: C,
+    pub state: S,
+    pub config: KeeperConfig,
+}
+
+impl<C: GovChain, S: KeeperState> GovKeeper<C, S> {
+    pub fn new(chain: C, state: S, config: KeeperConfig) -> Self {
+        Self {
+            chain,
+            state,
+            config,
+        }
+    }
+
+    /// 持续运行，单次检查失败只记录日志
+    pub async fn run(&self) {
+        loop {
+            if let Err(e) = self.tick().await {
+                log::error!("gov keeper tick failed: {:?}", e);
+            }
+            tokio::time::sleep(self.config.interval).await;
+        }
+    }
+
+    /// 检查所有 DAO 一次，返回本次执行的操作
+    pub async fn tick(&self) -> anyhow::Result<Vec<KeeperOutcome>> {
+        let now = self.chain.block_number().await?;
+        let mut outcomes = vec![];
+        for dao_id in self.config.dao_ids.iter() {
+            match self.tick_dao(*dao_id, now).await {
+                Ok(list) => outcomes.extend(list),
+                Err(e) => log::warn!("gov keeper dao {} failed: {:?}", dao_id, e),
+            }
+        }
+        Ok(outcomes)
+    }
+
+    async fn tick_dao(&self, dao_id: u64, now: u64) -> anyhow::Result<Vec<KeeperOutcome>> {
+        let periods = self.chain.periods(dao_id).await?;
+        let referendums = self.chain.referendums(dao_id).await?;
+        let mut outcomes = vec![];
+
+        if self.config.auto_start {
+            // 每个周期进行中的投票数，本次开始成功后加一
+            let mut deciding: HashMap<u32, u32> = HashMap::new();
+            for r in referendums.iter().filter(|r| matches!(r.status, ReferendumStatus::Ongoing)) {
+                *deciding.entry(r.period_index).or_default() += 1;
+            }
+            for proposal in self.chain.proposals(dao_id).await?.iter() {
+                let period = match periods.iter().find(|p| p.accepts(&proposal.call)) {
+                    Some(period) => period,
+                    None => {
+                        log::warn!(
+                            "dao {} proposal {} ({}) has no gov period",
+                            dao_id,
+                            proposal.index,
+                            proposal.call_name
+                        );
+                        continue;
+                    }
+                };
+                // 同一周期同时进行的投票不超过上限
+                let count = deciding.entry(period.index).or_default();
+                if *count >= period.max_deciding {
+                    continue;
+                }
+                let key = KeeperKey {
+                    dao_id,
+                    kind: KeeperActionKind::Start,
+                    index: proposal.index,
+                };
+                let action = self.chain.start_referendum(
+                    &self.config.signer,
+                    dao_id,
+                    proposal.index,
+                    period.decision_deposit,
+                );
+                if let Some(outcome) = self.attempt(key, now, action).await? {
+                    if outcome.error.is_none() {
+                        *count += 1;
+                    }
+                    outcomes.push(outcome);
+                }
+            }
+        }
+
+        if self.config.auto_run {
+            for referendum in referendums.iter() {
+                if !matches!(referendum.status, ReferendumStatus::Ongoing) {
+                    continue;
+                }
+                let end: u64 = referendum.end.into();
+                if now < end {
+                    continue;
+                }
+                if !referendum.is_passing() {
+                    let key = KeeperKey {
+                        dao_id,
+                        kind: KeeperActionKind::Rejected,
+                        index: referendum.index,
+                    };
+                    if self.state.last_attempt(&key).is_none() {
+                        log::info!("dao {} referendum {} rejected", dao_id, referendum.index);
+                        self.state.record(&key, now)?;
+                    }
+                    continue;
+                }
+                let enactment: u64 = periods
+                    .iter()
+                    .find(|p| p.index == referendum.period_index)
+                    .map_or(0, |p| p.min_enactment_period.into());
+                if now < end + enactment {
+                    continue;
+                }
+                let key = KeeperKey {
+                    dao_id,
+                    kind: KeeperActionKind::Run,
+                    index: referendum.index,
+                };
+                let action = self.chain.run_proposal(&self.config.signer, dao_id, referendum.index);
+                if let Some(outcome) = self.attempt(key, now, action).await? {
+                    outcomes.push(outcome);
+                }
+            }
+        }
+
+        Ok(outcomes)
+    }
+
+    // 距离上次提交不足 retry_after 个区块时跳过，否则提交并记录
+    async fn attempt(
+        &self,
+        key: KeeperKey,
+        now: u64,
+        action: BoxFuture<'_, anyhow::Result<()>>,
+    ) -> anyhow::Result<Option<KeeperOutcome>> {
+        if let Some(last) = self.state.last_attempt(&key) {
+            if now < last + self.config.retry_after {
+                return Ok(None);
+            }
+        }
+        self.state.record(&key, now)?;
+
+        let error = action.await.err().map(|e| format!("{:?}", e));
+        match &error {
+            None => log::info!("dao {} {:?} {} submitted at block {}", key.dao_id, key.kind, key.index, now),
+            Some(e) => log::warn!("dao {} {:?} {} failed: {}", key.dao_id, key.kind, key.index, e),
+        }
+        Ok(Some(KeeperOutcome {
+            dao_id: key.dao_id,
+            kind: key.kind,
+            index: key.index,
+            block_number: now,
+            error,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use wetee_gov::MemmberData;
+    use wetee_runtime::{AccountId, BlockNumber, Hash, RuntimeCall, WeteeProjectCall};
+
+    use super::*;
+    use crate::model::gov::{call_name, call_pallet_index};
+
+    const DAO_ID: u64 = 5000;
+
+    fn call() -> RuntimeCall {
+        RuntimeCall::WeteeProject(WeteeProjectCall::apply_project_funds {
+            dao_id: DAO_ID,
+            project_id: 1,
+            amount: 10,
+        })
+    }
+
+    fn period(index: u32, max_deciding: u32, min_enactment_period: BlockNumber) -> GovPeriod {
+        GovPeriod {
+            index,
+            name: "funds".to_owned(),
+            pallet_index: call_pallet_index(&call()),
+            decision_deposit: 5,
+            prepare_period: 0,
+            decision_period: 10,
+            confirm_period: 0,
+            min_enactment_period,
+            max_deciding,
+            min_approval: 50,
+            min_turnout: 0,
+        }
+    }
+
+    fn proposal(index: u32) -> Proposal {
+        Proposal {
+            dao_id: DAO_ID,
+            index,
+            hash: Hash::repeat_byte(index as u8),
+            call: call(),
+            call_name: call_name(&call()),
+            member: MemmberData::GLOBAL,
+            proposer: AccountId::new([1; 32]),
+        }
+    }
+
+    fn referendum(index: u32, period_index: u32, end: BlockNumber, yes: u128) -> ReferendumView {
+        ReferendumView {
+            dao_id: DAO_ID,
+            index,
+            call: call(),
+            call_name: call_name(&call()),
+            member: MemmberData::GLOBAL,
+            period_index,
+            status: ReferendumStatus::Ongoing,
+            yes,
+            no: 1,
+            approval: 0,
+            turnout: 0,
+            min_approval: None,
+            min_turnout: None,
+            end,
+            blocks_left: 0,
+            estimated_end: 0,
+        }
+    }
+
+    struct MockChain {
+        now: Mutex<u64>,
+        periods: Vec<GovPeriod>,
+        proposals: Vec<Proposal>,
+        referendums: Vec<ReferendumView>,
+        fail: Mutex<bool>,
+        submitted: Mutex<Vec<(KeeperActionKind, u32)>>,
+    }
+
+    impl MockChain {
+        fn new(periods: Vec<GovPeriod>, proposals: Vec<Proposal>, referendums: Vec<ReferendumView>) -> Self {
+            Self {
+                now: Mutex::new(100),
+                periods,
+                proposals,
+                referendums,
+                fail: Mutex::new(false),
+                submitted: Mutex::new(vec![]),
+            }
+        }
+
+        fn submit(&self, kind: KeeperActionKind, index: u32) -> anyhow::Result<()> {
+            self.submitted.lock().unwrap().push((kind, index));
+            if *self.fail.lock().unwrap() {
+                return Err(anyhow::anyhow!("rejected by node"));
+            }
+            Ok(())
+        }
+    }
+
+    impl GovChain for MockChain {
+        fn block_number(&self) -> BoxFuture<'_, anyhow::Result<u64>> {
+            Box::pin(async move { Ok(*self.now.lock().unwrap()) })
+        }
+
+        fn periods(&self, _dao_id: u64) -> BoxFuture<'_, anyhow::Result<Vec<GovPeriod>>> {
+            Box::pin(async move { Ok(self.periods.clone()) })
+        }
+
+        fn proposals(&self, _dao_id: u64) -> BoxFuture<'_, anyhow::Result<Vec<Proposal>>> {
+            Box::pin(async move { Ok(self.proposals.clone()) })
+        }
+
+        fn referendums(&self, _dao_id: u64) -> BoxFuture<'_, anyhow::Result<Vec<ReferendumView>>> {
+            Box::pin(async move { Ok(self.referendums.clone()) })
+        }
+
+        fn start_referendum<'a>(
+            &'a self,
+            _signer: &'a str,
+            _dao_id: u64,
+            propose_id: u32,
+            _deposit: u128,
+        ) -> BoxFuture<'a, anyhow::Result<()>> {
+            Box::pin(async move { self.submit(KeeperActionKind::Start, propose_id) })
+        }
+
+        fn run_proposal<'a>(
+            &'a self,
+            _signer: &'a str,
+            _dao_id: u64,
+            index: u32,
+        ) -> BoxFuture<'a, anyhow::Result<()>> {
+            Box::pin(async move { self.submit(KeeperActionKind::Run, index) })
+        }
+    }
+
+    fn keeper(chain: MockChain, state: MemoryKeeperState) -> GovKeeper<MockChain, MemoryKeeperState> {
+        GovKeeper::new(
+            chain,
+            state,
+            KeeperConfig {
+                dao_ids: vec![DAO_ID],
+                signer: "//Alice".to_owned(),
+                retry_after: 5,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn set_now(keeper: &GovKeeper<MockChain, MemoryKeeperState>, now: u64) {
+        *keeper.chain.now.lock().unwrap() = now;
+    }
+
+    fn submitted(keeper: &GovKeeper<MockChain, MemoryKeeperState>) -> Vec<(KeeperActionKind, u32)> {
+        keeper.chain.submitted.lock().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn start_respects_max_deciding() {
+        let chain = MockChain::new(
+            vec![period(3, 2, 0)],
+            vec![proposal(0), proposal(1), proposal(2)],
+            vec![referendum(7, 3, 200, 10)],
+        );
+        let keeper = keeper(chain, MemoryKeeperState::default());
+        let outcomes = keeper.tick().await.unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(submitted(&keeper), vec![(KeeperActionKind::Start, 0)]);
+    }
+
+    #[tokio::test]
+    async fn run_after_enactment_period() {
+        // 周期序号与在列表中的位置不同
+        let chain = MockChain::new(
+            vec![period(1, 1, 5), period(0, 1, 0)],
+            vec![],
+            vec![referendum(4, 1, 100, 10), referendum(5, 1, 100, 0)],
+        );
+        let keeper = keeper(chain, MemoryKeeperState::default());
+        set_now(&keeper, 104);
+        assert!(keeper.tick().await.unwrap().is_empty());
+
+        set_now(&keeper, 105);
+        let outcomes = keeper.tick().await.unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].error, None);
+        // 未通过的投票只记录，不执行
+        assert_eq!(submitted(&keeper), vec![(KeeperActionKind::Run, 4)]);
+        let rejected = KeeperKey {
+            dao_id: DAO_ID,
+            kind: KeeperActionKind::Rejected,
+            index: 5,
+        };
+        assert_eq!(keeper.state.last_attempt(&rejected), Some(104));
+    }
+
+    #[tokio::test]
+    async fn failed_start_retried_after_interval() {
+        let chain = MockChain::new(vec![period(0, 1, 0)], vec![proposal(0), proposal(1)], vec![]);
+        *chain.fail.lock().unwrap() = true;
+        let keeper = keeper(chain, MemoryKeeperState::default());
+
+        // 失败的开始不占用名额
+        let outcomes = keeper.tick().await.unwrap();
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().all(|o| o.error.is_some()));
+
+        set_now(&keeper, 104);
+        assert!(keeper.tick().await.unwrap().is_empty());
+
+        *keeper.chain.fail.lock().unwrap() = false;
+        set_now(&keeper, 105);
+        let outcomes = keeper.tick().await.unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].index, 0);
+        assert_eq!(submitted(&keeper).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn restart_does_not_resubmit() {
+        let chain = MockChain::new(vec![period(0, 1, 0)], vec![proposal(0)], vec![]);
+        let first = keeper(chain, MemoryKeeperState::default());
+        assert_eq!(first.tick().await.unwrap().len(), 1);
+
+        // 使用相同的记录重新启动，提案在链上仍未开始
+        let chain = MockChain::new(vec![period(0, 1, 0)], vec![proposal(0)], vec![]);
+        let second = keeper(chain, first.state);
+        set_now(&second, 102);
+        assert!(second.tick().await.unwrap().is_empty());
+        assert!(submitted(&second).is_empty());
+    }
+}
@@ -1,9 +1,14 @@
 pub mod account;
+pub mod batch;
 pub mod chain;
 pub mod client;
+pub mod describe;
 pub mod error_types;
 pub mod hander;
+pub mod indexer;
+pub mod keeper;
 pub mod model;
+pub mod proof;
 pub use client::Client;
 
 #[cfg(test)]
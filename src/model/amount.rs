@@ -0,0 +1,243 @@
+use std::fmt;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use wetee_assets::DaoAssetMeta;
+use wetee_runtime::Balance;
+
+use crate::chain::DECIMALS;
+
+/// 带精度的代币数量，raw 为链上的最小单位数量
+/// 不同精度的数量不能直接比较大小，不实现 Ord
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Amount {
+    raw: Balance,
+    decimals: u8,
+}
+
+impl Amount {
+    pub const fn from_raw(raw: Balance, decimals: u8) -> Self {
+        Self { raw, decimals }
+    }
+
+    /// 原生代币数量
+    pub const fn native(raw: Balance) -> Self {
+        Self::from_raw(raw, DECIMALS)
+    }
+
+    /// DAO 代币数量
+    pub fn asset(raw: Balance, meta: &DaoAssetMeta) -> Self {
+        Self::from_raw(raw, meta.decimals)
+    }
+
+    pub const fn zero(decimals: u8) -> Self {
+        Self::from_raw(0, decimals)
+    }
+
+    /// 精确解析十进制数量，例如 "1.5"，小数位超过精度或溢出时报错
+    pub fn parse(s: &str, decimals: u8) -> anyhow::Result<Self> {
+        let s = s.trim().replace('_', "");
+        let (int, frac) = match s.split_once('.') {
+            Some((int, frac)) => (int, frac),
+            None => (s.as_str(), ""),
+        };
+        if (int.is_empty() && frac.is_empty())
+            || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit())
+        {
+            return Err(anyhow::anyhow!("invalid amount {:?}", s));
+        }
+        let frac = frac.trim_end_matches('0');
+        if frac.len() > decimals as usize {
+            return Err(anyhow::anyhow!(
+                "amount {} has more than {} decimal places",
+                s,
+                decimals
+            ));
+        }
+
+        let overflow = || anyhow::anyhow!("amount {} overflows", s);
+        let base = 10u128.checked_pow(decimals as u32).ok_or_else(overflow)?;
+        let int: Balance = if int.is_empty() { 0 } else { int.parse().map_err(|_| overflow())? };
+        let frac: Balance = if frac.is_empty() {
+            0
+        } else {
+            frac.parse::<Balance>().map_err(|_| overflow())?
+                * 10u128.pow((decimals as usize - frac.len()) as u32)
+        };
+        let raw = int
+            .checked_mul(base)
+            .and_then(|v| v.checked_add(frac))
+            .ok_or_else(overflow)?;
+        Ok(Self { raw, decimals })
+    }
+
+    /// 解析原生代币数量
+    pub fn parse_native(s: &str) -> anyhow::Result<Self> {
+        Self::parse(s, DECIMALS)
+    }
+
+    pub const fn raw(&self) -> Balance {
+        self.raw
+    }
+
+    pub const fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    pub const fn is_zero(&self) -> bool {
+        self.raw == 0
+    }
+
+    /// 检查精度是否与代币一致，返回链上数量
+    pub fn ensure_decimals(&self, decimals: u8) -> anyhow::Result<Balance> {
+        if self.decimals != decimals {
+            return Err(anyhow::anyhow!(
+                "amount has {} decimals, token has {}",
+                self.decimals,
+                decimals
+            ));
+        }
+        Ok(self.raw)
+    }
+
+    /// 精度不同时返回 None
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        if self.decimals != other.decimals {
+            return None;
+        }
+        self.raw.checked_add(other.raw).map(|raw| Self { raw, ..self })
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        if self.decimals != other.decimals {
+            return None;
+        }
+        self.raw.checked_sub(other.raw).map(|raw| Self { raw, ..self })
+    }
+
+    pub fn checked_mul(self, n: u128) -> Option<Self> {
+        self.raw.checked_mul(n).map(|raw| Self { raw, ..self })
+    }
+
+    pub fn checked_div(self, n: u128) -> Option<Self> {
+        self.raw.checked_div(n).map(|raw| Self { raw, ..self })
+    }
+}
+
+impl From<Amount> for Balance {
+    fn from(amount: Amount) -> Self {
+        amount.raw
+    }
+}
+
+/// 按精度显示，去掉小数末尾的 0
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (int, frac) = match 10u128.checked_pow(self.decimals as u32) {
+            Some(base) => (self.raw / base, self.raw % base),
+            None => (0, self.raw),
+        };
+        if frac == 0 {
+            return write!(f, "{}", int);
+        }
+        let frac = format!("{:0width$}", frac, width = self.decimals as usize);
+        write!(f, "{}.{}", int, frac.trim_end_matches('0'))
+    }
+}
+
+// 序列化为 {"amount": "1.5", "decimals": 12}，避免 JSON 数字丢失精度
+#[derive(Serialize, Deserialize)]
+struct AmountRepr {
+    amount: String,
+    decimals: u8,
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        AmountRepr {
+            amount: self.to_string(),
+            decimals: self.decimals,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = AmountRepr::deserialize(deserializer)?;
+        Amount::parse(&repr.amount, repr.decimals).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(s: &str, decimals: u8) -> Balance {
+        Amount::parse(s, decimals).unwrap().raw()
+    }
+
+    #[test]
+    fn parse_decimal() {
+        assert_eq!(raw("1.5", 12), 1_500_000_000_000);
+        assert_eq!(raw(".5", 2), 50);
+        assert_eq!(raw("1.", 2), 100);
+        assert_eq!(raw("7", 0), 7);
+        assert_eq!(raw(" 1_000.25 ", 2), 100_025);
+        // 末尾的 0 不计入小数位
+        assert_eq!(raw("1.230", 2), 123);
+    }
+
+    #[test]
+    fn parse_rejects_invalid() {
+        for s in ["", ".", "1.2.3", "-1", "abc", "1e5", "1,5"] {
+            assert!(Amount::parse(s, 2).is_err(), "{:?}", s);
+        }
+        // 小数位超过精度
+        assert!(Amount::parse("1.234", 2).is_err());
+        assert!(Amount::parse("0.5", 0).is_err());
+    }
+
+    #[test]
+    fn parse_overflow() {
+        let max = u128::MAX.to_string();
+        assert_eq!(raw(&max, 0), u128::MAX);
+        assert!(Amount::parse(&format!("{}0", max), 0).is_err());
+        assert!(Amount::parse(&max, 1).is_err());
+        // 10 ^ 39 超出 u128
+        assert!(Amount::parse("1", 39).is_err());
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let cases = [
+            (Amount::from_raw(1_500, 3), "1.5"),
+            (Amount::from_raw(1_000, 3), "1"),
+            (Amount::from_raw(5, 3), "0.005"),
+            (Amount::from_raw(0, 12), "0"),
+            (Amount::from_raw(u128::MAX, 18), "340282366920938463463.374607431768211455"),
+        ];
+        for (amount, text) in cases.iter() {
+            assert_eq!(amount.to_string(), *text);
+            assert_eq!(Amount::parse(text, amount.decimals()).unwrap(), *amount);
+        }
+    }
+
+    #[test]
+    fn serde_as_string() {
+        let amount = Amount::from_raw(1_500, 3);
+        let json = serde_json::to_string(&amount).unwrap();
+        assert_eq!(json, r#"{"amount":"1.5","decimals":3}"#);
+        assert_eq!(serde_json::from_str::<Amount>(&json).unwrap(), amount);
+        assert!(serde_json::from_str::<Amount>(r#"{"amount":"1.2345","decimals":2}"#).is_err());
+    }
+
+    #[test]
+    fn arithmetic_requires_same_decimals() {
+        let a = Amount::from_raw(10, 2);
+        assert_eq!(a.checked_add(Amount::from_raw(5, 2)), Some(Amount::from_raw(15, 2)));
+        assert_eq!(a.checked_add(Amount::from_raw(5, 3)), None);
+        assert_eq!(a.checked_sub(Amount::from_raw(11, 2)), None);
+        assert!(a.ensure_decimals(3).is_err());
+        assert_eq!(a.ensure_decimals(2).unwrap(), 10);
+    }
+}
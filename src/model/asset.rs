@@ -0,0 +1,38 @@
+use sp_runtime::Permill;
+use wetee_assets::DaoAssetMeta;
+use wetee_runtime::{AccountId, Balance};
+
+use super::account::AssetAccountData;
+
+/// DAO 资产信息
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AssetInfo {
+    pub dao_id: u64,
+    pub metadata: DaoAssetMeta,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    // 发行总量
+    pub total_issuance: Balance,
+    // 最低存款
+    pub existential_deposit: Balance,
+}
+
+/// 资产持有人
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AssetHolder {
+    pub account: AccountId,
+    pub balance: AssetAccountData<Balance>,
+    // 可用与锁定余额之和
+    pub total: Balance,
+    // 占发行总量的比例
+    pub share: Permill,
+}
+
+/// 持有人分页结果，按持有量倒序排列
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct HolderPage {
+    pub total: u64,
+    pub total_issuance: Balance,
+    pub holders: Vec<AssetHolder>,
+}
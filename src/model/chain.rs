@@ -1,10 +1,14 @@
-use codec::{Decode, Encode};
+use codec::{Decode, Encode, Output};
+use futures::channel::mpsc::UnboundedReceiver;
+use serde::{Deserialize, Serialize};
 use sp_core::sr25519;
 use sp_runtime::traits::BlakeTwo256;
 use sp_runtime::AccountId32;
 use substrate_api_client::ac_primitives::{Config, AccountData, AssetTipExtrinsicParams, ExtrinsicSigner};
 use substrate_api_client::rpc::JsonrpseeClient;
 use substrate_api_client::Api;
+use substrate_api_client::ac_node_api::Metadata;
+use frame_metadata::v14::RuntimeMetadataV14;
 use tokio::sync::oneshot;
 use wetee_runtime::{RuntimeCall, Header, Block, Nonce, BlockNumber, Hash, AccountId, Address, Signature};
 
@@ -34,13 +38,192 @@ impl Config for WeteeConfig {
 // 用于存储客户端的连接
 pub type ChainApi = Api<WeteeConfig, JsonrpseeClient>;
 
+/// 分页
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Page {
+    pub offset: u64,
+    pub limit: u64,
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            limit: 20,
+        }
+    }
+}
+
+/// 区块引用，用于查询历史状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockRef {
+    // 区块哈希
+    Hash(Hash),
+    // 区块高度
+    Number(BlockNumber),
+}
+
+impl From<Hash> for BlockRef {
+    fn from(hash: Hash) -> Self {
+        BlockRef::Hash(hash)
+    }
+}
+
+impl From<BlockNumber> for BlockRef {
+    fn from(number: BlockNumber) -> Self {
+        BlockRef::Number(number)
+    }
+}
+
 /// 查询数据类型
-#[derive(Debug,Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum QueryKey{
     U64Key(u64),
     U32Key(u32),
     StrKey(String),
     AccountId(AccountId32),
+    // 已经 SCALE 编码的任意 key (H256, u128, Vec<u8>, 元组等)
+    Encoded(Vec<u8>),
+}
+
+impl QueryKey {
+    /// 由任意可编码类型构造 key
+    pub fn from_encode<K: Encode>(key: &K) -> Self {
+        QueryKey::Encoded(key.encode())
+    }
+}
+
+impl Encode for QueryKey {
+    fn size_hint(&self) -> usize {
+        match self {
+            QueryKey::U64Key(v) => v.size_hint(),
+            QueryKey::U32Key(v) => v.size_hint(),
+            QueryKey::StrKey(v) => v.size_hint(),
+            QueryKey::AccountId(v) => v.size_hint(),
+            QueryKey::Encoded(v) => v.len(),
+        }
+    }
+
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        match self {
+            QueryKey::U64Key(v) => v.encode_to(dest),
+            QueryKey::U32Key(v) => v.encode_to(dest),
+            QueryKey::StrKey(v) => v.encode_to(dest),
+            QueryKey::AccountId(v) => v.encode_to(dest),
+            QueryKey::Encoded(v) => dest.write(v),
+        }
+    }
+}
+
+impl From<u64> for QueryKey {
+    fn from(v: u64) -> Self {
+        QueryKey::U64Key(v)
+    }
+}
+
+impl From<u32> for QueryKey {
+    fn from(v: u32) -> Self {
+        QueryKey::U32Key(v)
+    }
+}
+
+impl From<String> for QueryKey {
+    fn from(v: String) -> Self {
+        QueryKey::StrKey(v)
+    }
+}
+
+impl From<AccountId32> for QueryKey {
+    fn from(v: AccountId32) -> Self {
+        QueryKey::AccountId(v)
+    }
+}
+
+impl From<Hash> for QueryKey {
+    fn from(v: Hash) -> Self {
+        QueryKey::from_encode(&v)
+    }
+}
+
+impl From<u128> for QueryKey {
+    fn from(v: u128) -> Self {
+        QueryKey::from_encode(&v)
+    }
+}
+
+impl From<Vec<u8>> for QueryKey {
+    fn from(v: Vec<u8>) -> Self {
+        QueryKey::from_encode(&v)
+    }
+}
+
+/// 批量查询中的单个存储项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageQuery {
+    Value {
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+    },
+    Map {
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+        key: QueryKey,
+    },
+    DoubleMap {
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+        first: QueryKey,
+        second: QueryKey,
+    },
+    NMap {
+        storage_prefix: &'static str,
+        storage_key_name: &'static str,
+        keys: Vec<QueryKey>,
+    },
+}
+
+/// 存储变化通知
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageChange<V> {
+    // 发生变化的区块
+    pub block: Hash,
+    // 变化后的值，None 表示已删除
+    pub value: Option<V>,
+}
+
+/// 存储变化订阅
+pub type StorageStream<V> = UnboundedReceiver<anyhow::Result<StorageChange<V>>>;
+
+/// 存储读取证明
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageReadProof {
+    // 证明所在区块
+    pub at: Hash,
+    // 区块头中的状态根
+    pub state_root: Hash,
+    // 存储键
+    pub key: Vec<u8>,
+    // trie 节点
+    pub proof: Vec<Vec<u8>>,
+}
+
+/// 区块原始数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainBlock {
+    // 区块高度
+    pub number: BlockNumber,
+    // 区块哈希
+    pub hash: Hash,
+    // 父区块哈希
+    pub parent_hash: Hash,
+    // 区块时间戳 (毫秒)
+    pub timestamp: u64,
+    // 区块所用 runtime 的版本
+    pub spec_version: u32,
+    // SCALE 编码的交易
+    pub extrinsics: Vec<Vec<u8>>,
+    // SCALE 编码的 System::Events
+    pub events: Vec<u8>,
 }
 
 /// 命令
@@ -49,20 +232,35 @@ pub enum Command {
     QueryBlockNumber {
         resp: Responder<u64>,
     },
+    QueryBlock {
+        number: BlockNumber,
+        resp: Responder<Option<ChainBlock>>,
+    },
+    QueryMetadataAt {
+        hash: Hash,
+        resp: Responder<Metadata>,
+    },
+    QueryRuntimeMetadata {
+        hash: Option<Hash>,
+        resp: Responder<RuntimeMetadataV14>,
+    },
     QueryValue {
 		storage_prefix: &'static str,
 		storage_key_name: &'static str,
+        at: Option<BlockRef>,
         resp: Responder<Option<Vec<u8>>>,
     },
     QueryMap {
 		storage_prefix: &'static str,
 		storage_key_name: &'static str,
         key: QueryKey,
+        at: Option<BlockRef>,
         resp: Responder<Option<Vec<u8>>>,
     },
     QueryMapAll {
 		storage_prefix: &'static str,
 		storage_key_name: &'static str,
+        at: Option<BlockRef>,
         resp: Responder<Vec<(String, Vec<u8>)>>,
     },
     QueryDoubleMap {
@@ -70,18 +268,48 @@ pub enum Command {
 		storage_key_name: &'static str,
         first: QueryKey,
         second: QueryKey,
+        at: Option<BlockRef>,
         resp: Responder<Option<Vec<u8>>>,
     },
     QueryDoubleMapFirst {
 		storage_prefix: &'static str,
 		storage_key_name: &'static str,
         first: QueryKey,
+        at: Option<BlockRef>,
+        resp: Responder<Vec<(String, Vec<u8>)>>,
+    },
+    QueryNMap {
+		storage_prefix: &'static str,
+		storage_key_name: &'static str,
+        keys: Vec<QueryKey>,
+        at: Option<BlockRef>,
+        resp: Responder<Option<Vec<u8>>>,
+    },
+    QueryNMapPrefix {
+		storage_prefix: &'static str,
+		storage_key_name: &'static str,
+        keys: Vec<QueryKey>,
+        at: Option<BlockRef>,
         resp: Responder<Vec<(String, Vec<u8>)>>,
     },
+    QueryStorageMany {
+        queries: Vec<StorageQuery>,
+        at: Option<BlockRef>,
+        resp: Responder<Vec<Option<Vec<u8>>>>,
+    },
+    QueryStorageKey {
+        query: StorageQuery,
+        resp: Responder<Vec<u8>>,
+    },
+    QueryStorageProof {
+        query: StorageQuery,
+        at: Option<BlockRef>,
+        resp: Responder<StorageReadProof>,
+    },
     SubmitExtrinsic{
         call: RuntimeCall,
         signer: String,
-        resp: Responder<String>,
+        resp: Responder<Hash>,
     },
     Close,
 }
@@ -1,7 +1,88 @@
 use codec::{Decode, Encode};
-use wetee_org::QuarterTask;
+use serde::{Deserialize, Serialize};
+use wetee_org::{OrgApp, OrgInfo, QuarterTask};
+use crate::model::account::AssetAccountData;
 use wetee_gov::MemmberData;
-use wetee_runtime::AccountId;
+use wetee_runtime::{AccountId, Balance, BlockNumber, Hash};
+
+/// 里程碑任务状态：已完成
+pub const ROADMAP_TASK_DONE: u8 = 2;
+
+/// 里程碑 ID，链上以 年 * 100 + 季度 保存
+/// 只能通过 new 或 TryFrom<u32> 创建，季度始终为 1 到 4
+#[derive(
+    Encode, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize,
+)]
+#[serde(try_from = "u32", into = "u32")]
+pub struct RoadmapId(u32);
+
+impl RoadmapId {
+    /// 季度为 1 到 4
+    pub fn new(year: u32, quarter: u32) -> anyhow::Result<Self> {
+        if !(1..=4).contains(&quarter) {
+            return Err(anyhow::anyhow!("invalid quarter {}", quarter));
+        }
+        let id = year
+            .checked_mul(100)
+            .ok_or_else(|| anyhow::anyhow!("invalid year {}", year))?;
+        Ok(Self(id + quarter))
+    }
+
+    pub fn year(&self) -> u32 {
+        self.0 / 100
+    }
+
+    pub fn quarter(&self) -> u32 {
+        self.0 % 100
+    }
+
+    /// 下一个季度
+    pub fn next(&self) -> Self {
+        if self.quarter() >= 4 {
+            Self((self.year() + 1) * 100 + 1)
+        } else {
+            Self(self.0 + 1)
+        }
+    }
+
+    /// from 到 to 之间的所有季度，包含两端
+    pub fn range(from: RoadmapId, to: RoadmapId) -> Vec<RoadmapId> {
+        let mut ids = vec![];
+        let mut id = from;
+        while id <= to {
+            ids.push(id);
+            id = id.next();
+        }
+        ids
+    }
+}
+
+impl From<RoadmapId> for u32 {
+    fn from(id: RoadmapId) -> Self {
+        id.0
+    }
+}
+
+impl TryFrom<u32> for RoadmapId {
+    type Error = anyhow::Error;
+
+    fn try_from(id: u32) -> anyhow::Result<Self> {
+        Self::new(id / 100, id % 100)
+    }
+}
+
+// 从存储解码时同样校验季度
+impl Decode for RoadmapId {
+    fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
+        Self::try_from(u32::decode(input)?).map_err(|_| "invalid roadmap id".into())
+    }
+}
+
+impl std::fmt::Display for RoadmapId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-Q{}", self.year(), self.quarter())
+    }
+}
 
 /// balance information for an account.
 #[derive(Encode, Decode, Clone, PartialEq, Eq, Default, Debug)]
@@ -14,14 +95,101 @@ pub struct Quarter {
     pub tasks: Vec<QuarterTask<AccountId>>,
 }
 
-/// vote yes or no
-/// 投票
-#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
-pub struct WithGov {
-    // 1 => gov
-    // 2 => sudo
-    pub run_type: u8,
-    pub amount: u128,
-    pub member: MemmberData,
-    pub period_index: u32,
+impl Quarter {
+    pub fn id(&self) -> anyhow::Result<RoadmapId> {
+        RoadmapId::new(self.year, self.quarter)
+    }
+
+    /// 只保留包含任一标签的任务，tags 为空时不过滤
+    pub fn filter_tags(mut self, tags: &[u8]) -> Self {
+        if !tags.is_empty() {
+            self.tasks
+                .retain(|t| t.tags.iter().any(|tag| tags.contains(tag)));
+        }
+        self
+    }
+}
+
+/// 修改 DAO 状态的执行方式
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Execution {
+    // 签名账户直接执行
+    #[default]
+    Direct,
+    // 通过 WeteeSudo::sudo 执行
+    Sudo,
+    // 创建提案，投票通过后执行
+    // deposit 大于 0 时以该押金立即开始投票
+    Gov {
+        period: u32,
+        member: MemmberData,
+        deposit: Balance,
+    },
+}
+
+/// 执行结果
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Executed {
+    // 已在区块中执行
+    Immediately { block_hash: Hash },
+    // 创建了提案 index，started 表示是否已开始投票
+    Proposed {
+        index: u32,
+        started: bool,
+        block_hash: Hash,
+    },
+}
+
+impl Executed {
+    /// 创建的提案序号，立即执行时为 None
+    pub fn proposal(&self) -> Option<u32> {
+        match self {
+            Executed::Proposed { index, .. } => Some(*index),
+            Executed::Immediately { .. } => None,
+        }
+    }
+}
+
+/// DAO 概要信息，一次请求获取
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DaoSummary {
+    // DAO 信息
+    pub info: OrgInfo<AccountId, BlockNumber>,
+    // 成员
+    pub members: Vec<AccountId>,
+    // 发行货币总量
+    pub total_issuance: u128,
+    // 组织应用
+    pub apps: Vec<OrgApp<BlockNumber>>,
+}
+
+/// 项目的任务统计
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ProjectTaskCount {
+    pub project_id: u64,
+    // 未完成的任务
+    pub open_tasks: u32,
+    pub total_tasks: u32,
+}
+
+/// DAO 总览
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DaoOverview {
+    // DAO 信息
+    pub info: OrgInfo<AccountId, BlockNumber>,
+    pub member_count: u32,
+    pub guild_count: u32,
+    pub project_count: u32,
+    // 发行货币总量
+    pub total_issuance: u128,
+    // 国库账户的原生代币余额
+    pub treasury: AssetAccountData<u128>,
+    // 国库账户的 DAO 资产余额
+    pub treasury_asset: AssetAccountData<u128>,
+    // 进行中的投票
+    pub active_referendums: Vec<u32>,
+    // 每个项目的任务统计
+    pub project_tasks: Vec<ProjectTaskCount>,
+    // 按声誉排序的成员
+    pub leaderboard: Vec<(AccountId, u32)>,
 }
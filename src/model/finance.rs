@@ -0,0 +1,149 @@
+use wetee_project::TaskStatus;
+use wetee_runtime::{AccountId, Balance};
+
+use super::{account::AssetAccountData, task::TaskView};
+
+/// 贡献者在项目中的奖励
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ContributorFinance {
+    pub account: AccountId,
+    // 未完成任务中的预计奖励
+    pub budget: Balance,
+    // 已完成任务的奖励
+    pub paid: Balance,
+    pub tasks_done: u32,
+}
+
+/// 项目资金报表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectFinance {
+    pub dao_id: u64,
+    pub project_id: u64,
+    pub project_account: AccountId,
+    // 已申请的资金，包括提案执行的申请，需要索引数据库，否则为 None
+    pub funds_applied: Option<Balance>,
+    // 所有任务的奖励总额
+    pub budget: Balance,
+    // 已完成任务的奖励
+    pub paid: Balance,
+    // 未完成任务的奖励
+    pub committed: Balance,
+    // 项目账户的 DAO 资产余额
+    pub balance: AssetAccountData<Balance>,
+    // 余额减去未完成任务的奖励，不足时为负数
+    pub remaining: i128,
+    pub contributors: Vec<ContributorFinance>,
+}
+
+impl ProjectFinance {
+    /// 根据任务和项目账户余额计算报表，奖励由执行人平分
+    pub fn build(
+        dao_id: u64,
+        project_id: u64,
+        project_account: AccountId,
+        tasks: &[TaskView],
+        balance: AssetAccountData<Balance>,
+        funds_applied: Option<Balance>,
+    ) -> Self {
+        let mut contributors: Vec<ContributorFinance> = vec![];
+        let (mut budget, mut paid) = (0u128, 0u128);
+        for task in tasks.iter() {
+            let done = matches!(task.status, TaskStatus::Done);
+            budget = budget.saturating_add(task.amount);
+            if done {
+                paid = paid.saturating_add(task.amount);
+            }
+
+            let count = task.assignees.len() as u128;
+            for (i, assignee) in task.assignees.iter().enumerate() {
+                // 余数分给第一个执行人
+                let share = task.amount / count + if i == 0 { task.amount % count } else { 0 };
+                let index = match contributors.iter().position(|c| &c.account == assignee) {
+                    Some(index) => index,
+                    None => {
+                        contributors.push(ContributorFinance {
+                            account: assignee.clone(),
+                            ..Default::default()
+                        });
+                        contributors.len() - 1
+                    }
+                };
+                let c = &mut contributors[index];
+                if done {
+                    c.paid = c.paid.saturating_add(share);
+                    c.tasks_done += 1;
+                } else {
+                    c.budget = c.budget.saturating_add(share);
+                }
+            }
+        }
+        contributors.sort_by(|a, b| (b.paid + b.budget).cmp(&(a.paid + a.budget)));
+
+        let committed = budget - paid;
+        Self {
+            dao_id,
+            project_id,
+            project_account,
+            funds_applied,
+            budget,
+            paid,
+            committed,
+            remaining: balance.free as i128 - committed as i128,
+            balance,
+            contributors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(n: u8) -> AccountId {
+        AccountId::new([n; 32])
+    }
+
+    fn task(id: u64, status: TaskStatus, amount: Balance, assignees: &[u8]) -> TaskView {
+        TaskView {
+            id,
+            creator: account(9),
+            status,
+            priority: 0,
+            point: 0,
+            amount,
+            max_assignee: 3,
+            assignees: assignees.iter().map(|n| account(*n)).collect(),
+            reviewers: vec![],
+            skills: vec![],
+        }
+    }
+
+    #[test]
+    fn splits_rewards_between_assignees() {
+        let tasks = vec![
+            task(1, TaskStatus::Done, 101, &[1, 2]),
+            task(2, TaskStatus::InProgress, 60, &[2]),
+            task(3, TaskStatus::ToDo, 40, &[]),
+        ];
+        let balance = AssetAccountData { free: 80, ..Default::default() };
+        let report = ProjectFinance::build(5000, 1, account(7), &tasks, balance, Some(500));
+
+        assert_eq!(report.funds_applied, Some(500));
+        assert_eq!((report.budget, report.paid, report.committed), (201, 101, 100));
+        assert_eq!(report.remaining, -20);
+        assert_eq!(
+            report.contributors,
+            vec![
+                ContributorFinance { account: account(2), budget: 60, paid: 50, tasks_done: 1 },
+                ContributorFinance { account: account(1), budget: 0, paid: 51, tasks_done: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_project() {
+        let report = ProjectFinance::build(5000, 1, account(7), &[], Default::default(), None);
+        assert_eq!((report.budget, report.paid, report.committed, report.remaining), (0, 0, 0, 0));
+        assert!(report.contributors.is_empty());
+    }
+}
@@ -0,0 +1,239 @@
+use codec::Encode;
+use frame_support::dispatch::GetCallMetadata;
+use wetee_gov::{MemmberData, Opinion, Period, ReferendumStatus};
+use wetee_runtime::{AccountId, Balance, BlockNumber, Hash, Pledge, RuntimeCall, WeteeSudoCall};
+
+/// 调用名称，例如 WeteeOrg::create_dao
+pub fn call_name(call: &RuntimeCall) -> String {
+    let meta = call.get_call_metadata();
+    format!("{}::{}", meta.pallet_name, meta.function_name)
+}
+
+/// 待开始的提案
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proposal {
+    pub dao_id: u64,
+    pub index: u32,
+    pub hash: Hash,
+    pub call: RuntimeCall,
+    pub call_name: String,
+    // 可以投票的成员范围
+    pub member: MemmberData,
+    pub proposer: AccountId,
+}
+
+/// 投票详情
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferendumView {
+    pub dao_id: u64,
+    pub index: u32,
+    pub call: RuntimeCall,
+    pub call_name: String,
+    pub member: MemmberData,
+    pub period_index: u32,
+    pub status: ReferendumStatus,
+    // 赞成和反对票
+    pub yes: Balance,
+    pub no: Balance,
+    // 赞成票占比 (百分比)
+    pub approval: u8,
+    // 投票量占发行总量的比例 (百分比)
+    pub turnout: u8,
+    // 治理周期要求的最低赞成比例和投票率，周期不存在时为 None
+    pub min_approval: Option<u8>,
+    pub min_turnout: Option<u8>,
+    // 结束区块
+    pub end: BlockNumber,
+    // 距离结束的区块数，已结束为 0
+    pub blocks_left: u64,
+    // 预计结束时间 (毫秒时间戳)
+    pub estimated_end: u64,
+}
+
+impl ReferendumView {
+    /// 按当前票数是否可以通过
+    pub fn is_passing(&self) -> bool {
+        match (self.min_approval, self.min_turnout) {
+            (Some(approval), Some(turnout)) => self.approval >= approval && self.turnout >= turnout,
+            _ => self.yes > self.no,
+        }
+    }
+}
+
+/// 百分比，分母为 0 时为 0
+pub fn percent(part: Balance, total: Balance) -> u8 {
+    if total == 0 {
+        return 0;
+    }
+    (part.saturating_mul(100) / total).min(100) as u8
+}
+
+/// 调用所属模块在 runtime 中的序号，治理周期按序号匹配调用
+/// sudo 调用使用内部调用的模块
+pub fn call_pallet_index(call: &RuntimeCall) -> u16 {
+    match call {
+        RuntimeCall::WeteeSudo(WeteeSudoCall::sudo { call, .. }) => call_pallet_index(call),
+        _ => call.encode()[0].into(),
+    }
+}
+
+/// 治理周期配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GovPeriod {
+    // 在 DAO 周期列表中的序号，即 period_index
+    pub index: u32,
+    pub name: String,
+    // 适用的模块序号
+    pub pallet_index: u16,
+    // 开始投票需要的押金
+    pub decision_deposit: Balance,
+    // 准备期
+    pub prepare_period: BlockNumber,
+    // 投票时长
+    pub decision_period: BlockNumber,
+    // 确认期
+    pub confirm_period: BlockNumber,
+    // 通过后最短执行延迟
+    pub min_enactment_period: BlockNumber,
+    // 同时进行的投票上限
+    pub max_deciding: u32,
+    // 最低赞成比例 (百分比)
+    pub min_approval: u8,
+    // 最低投票率 (百分比)
+    pub min_turnout: u8,
+}
+
+impl GovPeriod {
+    pub fn from_period(index: u32, period: Period<Balance, BlockNumber>) -> Self {
+        Self {
+            index,
+            name: String::from_utf8_lossy(&period.name).to_string(),
+            pallet_index: period.pallet_index,
+            decision_deposit: period.decision_deposit,
+            prepare_period: period.prepare_period,
+            decision_period: period.decision_period,
+            confirm_period: period.confirm_period,
+            min_enactment_period: period.min_enactment_period,
+            max_deciding: period.max_deciding,
+            min_approval: period.min_approval,
+            min_turnout: period.min_support,
+        }
+    }
+
+    /// 是否适用于调用
+    pub fn accepts(&self, call: &RuntimeCall) -> bool {
+        self.pallet_index == call_pallet_index(call)
+    }
+}
+
+/// 提案的成员范围，guild 和 project 同时为 None 时为全体成员
+pub fn member_scope(guild_id: Option<u64>, project_id: Option<u64>) -> MemmberData {
+    match (guild_id, project_id) {
+        (Some(guild_id), _) => MemmberData::GUILD(guild_id),
+        (None, Some(project_id)) => MemmberData::PROJECT(project_id),
+        (None, None) => MemmberData::GLOBAL,
+    }
+}
+
+/// 质押锁定的代币数量，无法从质押计算锁定数量时为 None
+pub fn pledge_amount(pledge: &Pledge<Balance>) -> Option<Balance> {
+    #[allow(unreachable_patterns)]
+    match pledge {
+        Pledge::FungToken(amount) => Some(*amount),
+        _ => None,
+    }
+}
+
+/// 投票预览
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VotePreview {
+    pub dao_id: u64,
+    pub referendum_index: u32,
+    pub pledge: Pledge<Balance>,
+    pub opinion: Opinion,
+    // 投票后锁定的数量，无法计算时为 None
+    pub locked: Option<Balance>,
+    // 账户在该投票中已有的质押，改投时会先撤销
+    pub existing: Option<Pledge<Balance>>,
+    // 投票结束区块和预计结束时间 (毫秒时间戳)，投票结束前不会解锁
+    // 解锁区块在投票时由链上计算并记录在 VotesOf，投票后通过 unlockable 查询
+    pub referendum_end: BlockNumber,
+    pub estimated_end: u64,
+}
+
+/// 单个投票的锁定情况
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedVote {
+    pub referendum_index: u32,
+    pub pledge: Pledge<Balance>,
+    // 锁定数量，无法从质押计算时为 None
+    pub amount: Option<Balance>,
+    pub unlock_block: BlockNumber,
+    // 投票是否仍在进行
+    pub ongoing: bool,
+    // 调用 unlock 时是否会释放
+    pub unlockable: bool,
+    // 不能释放的原因
+    pub reason: Option<String>,
+}
+
+/// 账户在 DAO 中的投票锁定情况
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unlockable {
+    pub dao_id: u64,
+    pub block_number: BlockNumber,
+    // 可以计算锁定数量的投票的合计
+    pub locked: Balance,
+    // 现在调用 unlock 可以释放的数量
+    pub unlockable: Balance,
+    // 下一个可以解锁的区块
+    pub next_unlock: Option<BlockNumber>,
+    pub votes: Vec<LockedVote>,
+}
+
+#[cfg(test)]
+mod tests {
+    use wetee_runtime::WeteeProjectCall;
+
+    use super::*;
+
+    #[test]
+    fn sudo_call_uses_inner_pallet() {
+        let inner = RuntimeCall::WeteeProject(WeteeProjectCall::apply_project_funds {
+            dao_id: 5000,
+            project_id: 1,
+            amount: 10,
+        });
+        let sudo = RuntimeCall::WeteeSudo(WeteeSudoCall::sudo {
+            dao_id: 5000,
+            call: Box::new(RuntimeCall::WeteeSudo(WeteeSudoCall::sudo {
+                dao_id: 5000,
+                call: Box::new(inner.clone()),
+            })),
+        });
+        let index = u16::from(inner.encode()[0]);
+        assert_ne!(index, u16::from(sudo.encode()[0]));
+        assert_eq!(call_pallet_index(&sudo), index);
+
+        let period = |pallet_index| GovPeriod {
+            index: 0,
+            name: String::new(),
+            pallet_index,
+            decision_deposit: 0,
+            prepare_period: 0,
+            decision_period: 0,
+            confirm_period: 0,
+            min_enactment_period: 0,
+            max_deciding: 1,
+            min_approval: 50,
+            min_turnout: 10,
+        };
+        assert!(period(index).accepts(&sudo));
+        assert!(!period(index + 1).accepts(&sudo));
+    }
+
+    #[test]
+    fn fung_token_pledge_amount() {
+        assert_eq!(pledge_amount(&Pledge::FungToken(100)), Some(100));
+    }
+}
@@ -1,5 +1,12 @@
 pub mod account;
+pub mod amount;
 pub mod dao;
 pub mod err;
 pub mod wetee;
-pub mod chain;
\ No newline at end of file
+pub mod asset;
+pub mod chain;
+pub mod finance;
+pub mod gov;
+pub mod reputation;
+pub mod snapshot;
+pub mod task;
@@ -0,0 +1,65 @@
+use wetee_runtime::AccountId;
+
+/// DAO 中的声誉
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaoPoints {
+    pub dao_id: u64,
+    pub point: u32,
+}
+
+/// 已完成的任务
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletedTask {
+    pub dao_id: u64,
+    pub project_id: u64,
+    pub task_id: u64,
+    pub name: Vec<u8>,
+    pub point: u16,
+    // 完成任务的区块，没有历史记录时为 None
+    pub block_number: Option<u64>,
+}
+
+/// 给出的评审
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GivenReview {
+    pub dao_id: u64,
+    pub project_id: u64,
+    pub task_id: u64,
+    pub block_number: u64,
+    pub success: bool,
+}
+
+/// 声誉变化及原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointChange {
+    pub dao_id: u64,
+    pub block_number: u64,
+    pub point: u32,
+    // 例如 task_done project 1 task 3
+    pub reason: String,
+}
+
+/// 贡献者档案
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContributorProfile {
+    pub account: AccountId,
+    pub points: Vec<DaoPoints>,
+    pub tasks_done: Vec<CompletedTask>,
+    pub reviews: Vec<GivenReview>,
+    // (DAO ID, 公会 ID)
+    pub guilds: Vec<(u64, u64)>,
+    // (DAO ID, 项目 ID)
+    pub projects: Vec<(u64, u64)>,
+    // 有历史记录时可以解释的声誉变化
+    pub point_changes: Vec<PointChange>,
+}
+
+/// 声誉排行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderboardEntry {
+    // 从 1 开始，声誉相同时排名相同
+    pub rank: u32,
+    pub account: AccountId,
+    pub point: u32,
+    pub tasks_done: u32,
+}
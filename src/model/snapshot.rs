@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use wetee_assets::DaoAssetMeta;
+use wetee_gov::Referendum;
+use wetee_org::{GuildInfo, OrgApp, OrgInfo, QuarterTask};
+use wetee_project::{ProjectInfo, TaskInfo};
+use wetee_runtime::{AccountId, Balance, BlockNumber, RuntimeCall};
+
+use super::{chain::BlockRef, dao::RoadmapId};
+
+/// 快照格式版本，格式不兼容时递增
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// DAO 在某个区块的完整快照
+/// 链上类型以 SCALE 编码的 hex 保存，保证可以无损读回
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DaoSnapshot {
+    pub version: u32,
+    pub dao_id: u64,
+    pub at: BlockRef,
+    #[serde(with = "scale_hex")]
+    pub info: OrgInfo<AccountId, BlockNumber>,
+    pub members: Vec<SnapshotMember>,
+    pub guilds: Vec<SnapshotGuild>,
+    pub projects: Vec<SnapshotProject>,
+    pub roadmaps: Vec<SnapshotRoadmap>,
+    // DAO 资产元数据，未创建资产时为 None
+    #[serde(with = "scale_hex")]
+    pub asset: Option<DaoAssetMeta>,
+    pub total_issuance: u128,
+    #[serde(with = "scale_hex")]
+    pub apps: Vec<OrgApp<BlockNumber>>,
+    // 进行中的投票
+    pub referendums: Vec<SnapshotReferendum>,
+}
+
+/// 成员和声誉
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotMember {
+    pub account: AccountId,
+    pub point: u32,
+}
+
+/// 公会和公会成员
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotGuild {
+    #[serde(with = "scale_hex")]
+    pub info: GuildInfo<AccountId, BlockNumber>,
+    pub members: Vec<AccountId>,
+}
+
+/// 项目、项目成员和任务
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotProject {
+    #[serde(with = "scale_hex")]
+    pub info: ProjectInfo<AccountId>,
+    pub members: Vec<AccountId>,
+    #[serde(with = "scale_hex")]
+    pub tasks: Vec<TaskInfo<AccountId, Balance>>,
+}
+
+/// 季度里程碑
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotRoadmap {
+    pub roadmap_id: RoadmapId,
+    #[serde(with = "scale_hex")]
+    pub tasks: Vec<QuarterTask<AccountId>>,
+}
+
+/// 投票
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotReferendum {
+    pub index: u32,
+    #[serde(with = "scale_hex")]
+    pub referendum: Referendum<BlockNumber, RuntimeCall, Balance>,
+}
+
+/// 按 ID 比较的变化
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Changes<K> {
+    pub added: Vec<K>,
+    pub removed: Vec<K>,
+    pub changed: Vec<K>,
+}
+
+impl<K> Changes<K> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// 两个快照之间的差异
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    pub info_changed: bool,
+    // 成员变化，声誉变化记为 changed
+    pub members: Changes<AccountId>,
+    pub guilds: Changes<u64>,
+    pub projects: Changes<u64>,
+    // (项目 ID, 任务 ID)
+    pub tasks: Changes<(u64, u64)>,
+    pub roadmaps: Changes<RoadmapId>,
+    pub asset_changed: bool,
+    // (旧值, 新值)
+    pub total_issuance: Option<(u128, u128)>,
+    pub apps_changed: bool,
+    pub referendums: Changes<u32>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        !self.info_changed
+            && self.members.is_empty()
+            && self.guilds.is_empty()
+            && self.projects.is_empty()
+            && self.tasks.is_empty()
+            && self.roadmaps.is_empty()
+            && !self.asset_changed
+            && self.total_issuance.is_none()
+            && !self.apps_changed
+            && self.referendums.is_empty()
+    }
+}
+
+impl DaoSnapshot {
+    /// 导出为 JSON
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// 从 JSON 读取，版本不一致时返回错误
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+        if version != SNAPSHOT_VERSION as u64 {
+            return Err(anyhow::anyhow!(
+                "unsupported snapshot version {}, expected {}",
+                version,
+                SNAPSHOT_VERSION
+            ));
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// 与较新的快照比较
+    pub fn diff(&self, newer: &DaoSnapshot) -> SnapshotDiff {
+        let old_tasks = self.tasks();
+        let new_tasks = newer.tasks();
+        SnapshotDiff {
+            info_changed: self.info != newer.info,
+            members: changes(&self.members, &newer.members, |m| m.account.clone()),
+            guilds: changes(&self.guilds, &newer.guilds, |g| g.info.id),
+            projects: changes(&self.projects, &newer.projects, |p| p.info.id),
+            tasks: changes(&old_tasks, &new_tasks, |t| (t.0, t.1.id)),
+            roadmaps: changes(&self.roadmaps, &newer.roadmaps, |r| r.roadmap_id),
+            asset_changed: self.asset != newer.asset,
+            total_issuance: if self.total_issuance != newer.total_issuance {
+                Some((self.total_issuance, newer.total_issuance))
+            } else {
+                None
+            },
+            apps_changed: self.apps != newer.apps,
+            referendums: changes(&self.referendums, &newer.referendums, |r| r.index),
+        }
+    }
+
+    // 所有任务和所属项目
+    fn tasks(&self) -> Vec<(u64, &TaskInfo<AccountId, Balance>)> {
+        self.projects
+            .iter()
+            .flat_map(|p| p.tasks.iter().map(move |t| (p.info.id, t)))
+            .collect()
+    }
+}
+
+// 按 key 比较两个列表
+fn changes<T: PartialEq, K: PartialEq + Clone>(
+    old: &[T],
+    new: &[T],
+    key: impl Fn(&T) -> K,
+) -> Changes<K> {
+    let mut result = Changes {
+        added: vec![],
+        removed: vec![],
+        changed: vec![],
+    };
+    for item in new.iter() {
+        match old.iter().find(|o| key(o) == key(item)) {
+            Some(o) if o != item => result.changed.push(key(item)),
+            Some(_) => {}
+            None => result.added.push(key(item)),
+        }
+    }
+    for item in old.iter() {
+        if !new.iter().any(|n| key(n) == key(item)) {
+            result.removed.push(key(item));
+        }
+    }
+    result
+}
+
+// 链上类型以 SCALE 编码的 hex 序列化
+mod scale_hex {
+    use codec::{Decode, Encode};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Encode, S: Serializer>(value: &T, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&("0x".to_owned() + &hex::encode(value.encode())))
+    }
+
+    pub fn deserialize<'de, T: Decode, D: Deserializer<'de>>(d: D) -> Result<T, D::Error> {
+        let s = String::deserialize(d)?;
+        let bytes = hex::decode(s.trim_start_matches("0x")).map_err(D::Error::custom)?;
+        T::decode(&mut bytes.as_slice()).map_err(|e| D::Error::custom(format!("{:?}", e)))
+    }
+}
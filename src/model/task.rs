@@ -0,0 +1,248 @@
+use std::cmp::Ordering;
+
+use codec::{Decode, Encode};
+use wetee_project::{ReviewOpinion, TaskInfo, TaskStatus};
+use wetee_runtime::{AccountId, Balance};
+
+use super::chain::Page;
+
+/// 查询、状态检查和报表使用的任务字段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskView {
+    pub id: u64,
+    pub creator: AccountId,
+    pub status: TaskStatus,
+    pub priority: u8,
+    pub point: u16,
+    pub amount: Balance,
+    pub max_assignee: u8,
+    pub assignees: Vec<AccountId>,
+    pub reviewers: Vec<AccountId>,
+    pub skills: Vec<u8>,
+}
+
+impl From<&TaskInfo<AccountId, Balance>> for TaskView {
+    fn from(task: &TaskInfo<AccountId, Balance>) -> Self {
+        Self {
+            id: task.id,
+            creator: task.creator.clone(),
+            status: task.status.clone(),
+            priority: task.priority,
+            point: task.point,
+            amount: task.amount,
+            max_assignee: task.max_assignee,
+            assignees: task.assignees.clone(),
+            reviewers: task.reviewers.clone(),
+            skills: task.skills.clone(),
+        }
+    }
+}
+
+/// 任务评审记录，与 WeteeProject::TaskReviews 的编码一致
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct TaskReview {
+    pub who: AccountId,
+    pub meta: Vec<u8>,
+    pub option: ReviewOpinion,
+}
+
+impl TaskReview {
+    pub fn approved(&self) -> bool {
+        matches!(self.option, ReviewOpinion::YES)
+    }
+}
+
+/// 任务排序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TaskSort {
+    #[default]
+    Id,
+    Priority,
+    Point,
+    Amount,
+}
+
+/// 任务查询条件，为空的条件不过滤
+#[derive(Debug, Clone, Default)]
+pub struct TaskQuery {
+    // 执行人
+    pub assignee: Option<AccountId>,
+    // 评审
+    pub reviewer: Option<AccountId>,
+    // 执行人或评审
+    pub involves: Option<AccountId>,
+    pub status: Vec<TaskStatus>,
+    // 包含任一技能
+    pub skills: Vec<u8>,
+    pub min_priority: Option<u8>,
+    pub max_priority: Option<u8>,
+    pub min_amount: Option<Balance>,
+    pub max_amount: Option<Balance>,
+    pub sort: TaskSort,
+    pub descending: bool,
+    pub page: Page,
+}
+
+/// 查询到的任务
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskHit {
+    pub dao_id: u64,
+    pub project_id: u64,
+    pub task: TaskInfo<AccountId, Balance>,
+}
+
+/// 任务分页结果
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TaskPage {
+    pub total: u64,
+    pub tasks: Vec<TaskHit>,
+}
+
+// 过滤和排序使用的键：dao_id、project_id 和任务字段
+type TaskKey = (u64, u64, TaskView);
+
+impl TaskQuery {
+    pub fn matches(&self, task: &TaskView) -> bool {
+        self.assignee.as_ref().map_or(true, |a| task.assignees.contains(a))
+            && self.reviewer.as_ref().map_or(true, |r| task.reviewers.contains(r))
+            && self.involves.as_ref().map_or(true, |w| {
+                task.assignees.contains(w) || task.reviewers.contains(w)
+            })
+            && (self.status.is_empty() || self.status.contains(&task.status))
+            && (self.skills.is_empty() || task.skills.iter().any(|s| self.skills.contains(s)))
+            && self.min_priority.map_or(true, |p| task.priority >= p)
+            && self.max_priority.map_or(true, |p| task.priority <= p)
+            && self.min_amount.map_or(true, |a| task.amount >= a)
+            && self.max_amount.map_or(true, |a| task.amount <= a)
+    }
+
+    fn compare(&self, a: &TaskKey, b: &TaskKey) -> Ordering {
+        let (a_dao, a_project, a) = a;
+        let (b_dao, b_project, b) = b;
+        let ordering = match self.sort {
+            TaskSort::Id => (a_project, a.id).cmp(&(b_project, b.id)),
+            TaskSort::Priority => a.priority.cmp(&b.priority),
+            TaskSort::Point => a.point.cmp(&b.point),
+            TaskSort::Amount => a.amount.cmp(&b.amount),
+        }
+        .then_with(|| (a_dao, a_project, a.id).cmp(&(b_dao, b_project, b.id)));
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    /// 过滤、排序并分页
+    pub fn apply(&self, tasks: Vec<TaskHit>) -> TaskPage {
+        let (total, tasks) = self.select(tasks, |t| {
+            (t.dao_id, t.project_id, TaskView::from(&t.task))
+        });
+        TaskPage { total, tasks }
+    }
+
+    // 返回过滤后的总数和当前页
+    fn select<T>(&self, items: Vec<T>, key: impl Fn(&T) -> TaskKey) -> (u64, Vec<T>) {
+        let mut items: Vec<(TaskKey, T)> = items
+            .into_iter()
+            .map(|item| (key(&item), item))
+            .filter(|(k, _)| self.matches(&k.2))
+            .collect();
+        items.sort_by(|a, b| self.compare(&a.0, &b.0));
+        (
+            items.len() as u64,
+            items
+                .into_iter()
+                .skip(self.page.offset as usize)
+                .take(self.page.limit as usize)
+                .map(|(_, item)| item)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(n: u8) -> AccountId {
+        AccountId::new([n; 32])
+    }
+
+    fn task(id: u64, status: TaskStatus, priority: u8, amount: Balance, assignee: u8) -> TaskView {
+        TaskView {
+            id,
+            creator: account(1),
+            status,
+            priority,
+            point: 0,
+            amount,
+            max_assignee: 1,
+            assignees: vec![account(assignee)],
+            reviewers: vec![account(9)],
+            skills: vec![id as u8 % 3],
+        }
+    }
+
+    fn tasks() -> Vec<TaskKey> {
+        vec![
+            (5000, 2, task(1, TaskStatus::ToDo, 3, 100, 2)),
+            (5000, 1, task(2, TaskStatus::InProgress, 1, 300, 3)),
+            (5001, 1, task(1, TaskStatus::Done, 2, 200, 2)),
+            (5000, 1, task(1, TaskStatus::ToDo, 2, 50, 2)),
+        ]
+    }
+
+    fn ids(query: &TaskQuery) -> (u64, Vec<(u64, u64, u64)>) {
+        let (total, page) = query.select(tasks(), |t| t.clone());
+        (total, page.into_iter().map(|(d, p, t)| (d, p, t.id)).collect())
+    }
+
+    #[test]
+    fn sorts_by_project_then_dao() {
+        let (total, list) = ids(&TaskQuery::default());
+        assert_eq!(total, 4);
+        assert_eq!(
+            list,
+            vec![(5000, 1, 1), (5001, 1, 1), (5000, 1, 2), (5000, 2, 1)]
+        );
+    }
+
+    #[test]
+    fn filters() {
+        let query = TaskQuery {
+            assignee: Some(account(2)),
+            status: vec![TaskStatus::ToDo],
+            ..Default::default()
+        };
+        assert_eq!(ids(&query), (2, vec![(5000, 1, 1), (5000, 2, 1)]));
+
+        let query = TaskQuery {
+            involves: Some(account(9)),
+            min_amount: Some(100),
+            max_priority: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(ids(&query), (2, vec![(5001, 1, 1), (5000, 1, 2)]));
+
+        let query = TaskQuery {
+            skills: vec![2],
+            ..Default::default()
+        };
+        assert_eq!(ids(&query).1, vec![(5000, 1, 2)]);
+    }
+
+    #[test]
+    fn sorts_descending_and_pages() {
+        let query = TaskQuery {
+            sort: TaskSort::Amount,
+            descending: true,
+            page: Page {
+                offset: 1,
+                limit: 2,
+            },
+            ..Default::default()
+        };
+        assert_eq!(ids(&query), (4, vec![(5001, 1, 1), (5000, 2, 1)]));
+    }
+}
@@ -0,0 +1,118 @@
+use codec::Decode;
+use sp_runtime::traits::BlakeTwo256;
+use sp_state_machine::{read_proof_check, StorageProof};
+use wetee_runtime::Hash;
+
+use crate::model::chain::StorageReadProof;
+
+/// 根据状态根校验 trie 证明，返回 key 对应的原始值
+/// 证明有效且 key 不存在时返回 None
+pub fn verify_storage_proof(
+    state_root: Hash,
+    key: &[u8],
+    proof: &[Vec<u8>],
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let trie_proof = StorageProof::new(proof.iter().cloned());
+    let mut values = read_proof_check::<BlakeTwo256, _>(state_root, trie_proof, [key])
+        .map_err(|e| anyhow::anyhow!("invalid storage proof: {:?}", e))?;
+
+    Ok(values.remove(key).flatten())
+}
+
+/// 使用可信的状态根校验证明并解码值
+/// 状态根应当来自可信的区块头，而不是证明本身
+/// expected_key 应当在本地计算 (例如 Client::get_storage_key)，不能使用节点返回的 key
+pub fn verify_and_decode<V: Decode>(
+    trusted_state_root: Hash,
+    expected_key: &[u8],
+    read_proof: &StorageReadProof,
+) -> anyhow::Result<Option<V>> {
+    if read_proof.state_root != trusted_state_root {
+        return Err(anyhow::anyhow!(
+            "state root mismatch: proof {:?}, trusted {:?}",
+            read_proof.state_root,
+            trusted_state_root
+        ));
+    }
+    if read_proof.key != expected_key {
+        return Err(anyhow::anyhow!(
+            "storage key mismatch: proof 0x{}, expected 0x{}",
+            hex::encode(&read_proof.key),
+            hex::encode(expected_key)
+        ));
+    }
+    let value = verify_storage_proof(trusted_state_root, expected_key, &read_proof.proof)?;
+    match value {
+        Some(storage) => Ok(Some(Decode::decode(&mut storage.as_slice())?)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+    use sp_core::storage::StateVersion;
+    use sp_state_machine::{prove_read, InMemoryBackend};
+
+    // 由内存 trie 生成的证明，与 state_getReadProof 返回的格式相同
+    fn fixture(keys: &[&[u8]]) -> (Hash, Vec<Vec<u8>>) {
+        let storage = vec![
+            (b"dao:1".to_vec(), Some(42u64.encode())),
+            (b"dao:2".to_vec(), Some(7u64.encode())),
+            (b"member:1".to_vec(), Some(vec![1u8, 2, 3].encode())),
+        ];
+        let backend: InMemoryBackend<BlakeTwo256> =
+            (vec![(None, storage)], StateVersion::V1).into();
+        let root = *backend.root();
+        let proof = prove_read(backend, keys).unwrap();
+        (root, proof.into_iter_nodes().collect())
+    }
+
+    fn read_proof(root: Hash, key: &[u8], proof: Vec<Vec<u8>>) -> StorageReadProof {
+        StorageReadProof {
+            at: Hash::zero(),
+            state_root: root,
+            key: key.to_vec(),
+            proof,
+        }
+    }
+
+    #[test]
+    fn decodes_proven_value() {
+        let (root, proof) = fixture(&[b"dao:1"]);
+        let value: Option<u64> =
+            verify_and_decode(root, b"dao:1", &read_proof(root, b"dao:1", proof)).unwrap();
+        assert_eq!(value, Some(42));
+    }
+
+    #[test]
+    fn proves_absent_key() {
+        let (root, proof) = fixture(&[b"dao:3"]);
+        let value: Option<u64> =
+            verify_and_decode(root, b"dao:3", &read_proof(root, b"dao:3", proof)).unwrap();
+        assert_eq!(value, None);
+    }
+
+    #[test]
+    fn rejects_untrusted_root() {
+        let (root, proof) = fixture(&[b"dao:1"]);
+        let other = Hash::repeat_byte(1);
+        assert!(verify_and_decode::<u64>(other, b"dao:1", &read_proof(root, b"dao:1", proof)).is_err());
+    }
+
+    #[test]
+    fn rejects_substituted_key() {
+        // 节点证明了另一个存储项
+        let (root, proof) = fixture(&[b"dao:2"]);
+        let substituted = read_proof(root, b"dao:2", proof);
+        assert!(verify_and_decode::<u64>(root, b"dao:1", &substituted).is_err());
+    }
+
+    #[test]
+    fn rejects_incomplete_proof() {
+        let (root, _) = fixture(&[b"dao:1"]);
+        let (_, other_proof) = fixture(&[b"member:1"]);
+        assert!(verify_and_decode::<u64>(root, b"dao:1", &read_proof(root, b"dao:1", other_proof)).is_err());
+    }
+}